@@ -0,0 +1,199 @@
+use crate::{clamp, units::Pressure};
+
+#[derive(Clone)]
+pub(crate) struct Actuator {
+    min_position: f64,  // Deflection limit in the negative direction (position)
+    max_position: f64,  // Deflection limit in the positive direction (position)
+    rate_limit: f64,    // Maximum surface rate (position per second)
+    time_constant: f64, // Time constant of the first order servo lag (seconds)
+    blowback_pressure: Option<Pressure>, // Dynamic pressure above which full deflection can no longer be held
+    irreversible: bool, // True if the surface holds its position when it is no longer driven
+    position: f64,
+}
+
+impl Actuator {
+    // Rate (in position per second) at which an unpowered surface is faired by the airflow
+    const DAMPED_RATE: f64 = 0.2;
+
+    pub(crate) fn new(
+        min_position: f64,
+        max_position: f64,
+        rate_limit: f64,
+        time_constant: f64,
+    ) -> Actuator {
+        Actuator {
+            min_position,
+            max_position,
+            rate_limit,
+            time_constant,
+            blowback_pressure: None,
+            irreversible: false,
+            position: 0.0,
+        }
+    }
+
+    // Limits the deflection once the hinge moment exceeds what the servo can hold.
+    // The hinge moment grows with dynamic pressure times deflection, so above the blowback
+    // pressure the achievable deflection is inversely proportional to the dynamic pressure.
+    pub(crate) fn with_blowback(mut self, dynamic_pressure: Pressure) -> Actuator {
+        self.blowback_pressure = Some(dynamic_pressure);
+        self
+    }
+
+    // Holds the surface in place without hydraulic pressure, instead of letting the airflow fair it
+    pub(crate) fn irreversible(mut self) -> Actuator {
+        self.irreversible = true;
+        self
+    }
+
+    // Moves the surface to a position, e.g. the one it was left in
+    pub(crate) fn reset(&mut self, position: f64) -> f64 {
+        self.position = clamp(position, self.min_position, self.max_position);
+        self.position
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        command: f64,
+        powered: bool,
+        dynamic_pressure: Pressure,
+        dt: f64,
+    ) -> f64 {
+        // An irreversible surface stays where it is without hydraulic pressure
+        if !powered && self.irreversible {
+            return self.position;
+        }
+        // Without hydraulic pressure, the servo switches to damping mode and the surface is faired
+        // by the airflow instead of being driven.
+        if !powered {
+            let travel = Self::DAMPED_RATE * dt;
+            self.position = if self.position.abs() <= travel {
+                0.0
+            } else {
+                self.position - self.position.signum() * travel
+            };
+            return self.position;
+        }
+
+        let blowback = match self.blowback_pressure {
+            Some(pressure) if dynamic_pressure > pressure => pressure / dynamic_pressure,
+            _ => 1.0,
+        };
+        let target = clamp(
+            command,
+            self.min_position * blowback,
+            self.max_position * blowback,
+        );
+
+        // First order lag, bounded by the maximum surface rate
+        let rate = if self.time_constant > 0.0 {
+            (target - self.position) / self.time_constant
+        } else if dt > 0.0 {
+            (target - self.position) / dt
+        } else {
+            0.0
+        };
+        let rate = clamp(rate, -self.rate_limit, self.rate_limit);
+        let step = rate * dt;
+        // Don't overshoot the target on long frames
+        self.position = if step.abs() >= (target - self.position).abs() {
+            target
+        } else {
+            self.position + step
+        };
+
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    const DT: f64 = 0.02;
+
+    fn psf(pounds_per_square_foot: f64) -> Pressure {
+        Pressure::from_pounds_per_square_foot(pounds_per_square_foot)
+    }
+
+    // Drives an actuator with the same command for a duration, returning its position at the end
+    fn drive(actuator: &mut Actuator, command: f64, powered: bool, seconds: f64) -> f64 {
+        let mut position = actuator.position;
+        for _ in 0..(seconds / DT).round() as usize {
+            position = actuator.update(command, powered, psf(100.0), DT);
+        }
+        position
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut actuator = Actuator::new(-1.0, 1.0, 1.0, 0.05);
+
+        // The lag would move 20 times faster than the surface can
+        assert_close(drive(&mut actuator, 1.0, true, 0.2), 0.2, 1e-9);
+        assert_close(drive(&mut actuator, -1.0, true, 0.5), -0.3, 1e-9);
+    }
+
+    #[test]
+    fn position_limits() {
+        let mut actuator = Actuator::new(-0.5, 1.0, 10.0, 0.0);
+
+        assert_close(drive(&mut actuator, 2.0, true, 1.0), 1.0, 1e-12);
+        assert_close(drive(&mut actuator, -2.0, true, 1.0), -0.5, 1e-12);
+        assert_close(actuator.reset(3.0), 1.0, 1e-12);
+    }
+
+    #[test]
+    fn first_order_lag() {
+        let mut actuator = Actuator::new(-1.0, 1.0, 10.0, 0.5);
+
+        // 63% of a step after one time constant, and settled after five
+        let mut position = 0.0;
+        for _ in 0..500 {
+            position = actuator.update(0.1, true, psf(100.0), 0.001);
+        }
+        assert_close(position, 0.1 * (1.0 - (-1.0f64).exp()), 1e-4);
+        assert_close(drive(&mut actuator, 0.1, true, 2.0), 0.1, 1e-3);
+
+        // A long frame doesn't overshoot the command
+        let mut actuator = Actuator::new(-1.0, 1.0, 10.0, 0.05);
+        assert_close(actuator.update(0.1, true, psf(100.0), 1.0), 0.1, 1e-12);
+    }
+
+    #[test]
+    fn blowback() {
+        let mut actuator = Actuator::new(-1.0, 1.0, 10.0, 0.0).with_blowback(psf(380.0));
+
+        // Long frames, for the surface to reach each position
+        // Below the blowback pressure, the full deflection is held
+        assert_close(actuator.update(1.0, true, psf(300.0), 1.0), 1.0, 1e-12);
+        // Above it, the achievable deflection is inversely proportional to the dynamic pressure
+        assert_close(actuator.update(1.0, true, psf(760.0), 1.0), 0.5, 1e-12);
+        assert_close(actuator.update(-1.0, true, psf(760.0), 1.0), -0.5, 1e-12);
+        assert_close(actuator.update(0.3, true, psf(760.0), 1.0), 0.3, 1e-12);
+    }
+
+    #[test]
+    fn damped_without_hydraulics() {
+        let mut actuator = Actuator::new(-1.0, 1.0, 10.0, 0.0);
+        actuator.reset(0.5);
+
+        // The airflow fairs the surface, whatever the command
+        assert_close(drive(&mut actuator, 1.0, false, 1.0), 0.3, 1e-9);
+        assert_close(drive(&mut actuator, 1.0, false, 2.0), 0.0, 1e-12);
+
+        actuator.reset(-0.1);
+        assert_close(drive(&mut actuator, 0.0, false, 1.0), 0.0, 1e-12);
+    }
+
+    #[test]
+    fn irreversible_without_hydraulics() {
+        let mut actuator = Actuator::new(-4.0, 13.5, 1.0, 0.0).irreversible();
+        actuator.reset(5.0);
+
+        assert_close(drive(&mut actuator, 0.0, false, 5.0), 5.0, 1e-12);
+        // It moves again once powered
+        assert_close(drive(&mut actuator, 0.0, true, 1.0), 4.0, 1e-9);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::{
+    fbw::FBW,
+    units::{Angle, Length, Velocity},
+    Result,
+};
+use msfs::msfs::legacy::NamedVariable;
+
+#[derive(PartialEq, Clone, Copy)]
+enum AdrFault {
+    None,
+    Bias,   // The outputs are offset by a constant amount
+    Frozen, // The outputs are stuck at the values they had when the fault appeared
+    Noisy,  // The outputs are disturbed by random noise
+}
+impl From<f64> for AdrFault {
+    fn from(v: f64) -> Self {
+        match v as u8 {
+            1 => AdrFault::Bias,
+            2 => AdrFault::Frozen,
+            3 => AdrFault::Noisy,
+            _ => AdrFault::None,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct AirData {
+    ias: Velocity,    // The indicated airspeed
+    alpha: Angle,     // The angle of attack
+    altitude: Length, // The altitude
+}
+
+impl AirData {
+    // Maximum difference with the voted value before a channel is considered to disagree
+    // TODO: These are estimates, the real monitoring thresholds are not published
+    const IAS_THRESHOLD: Velocity = Velocity::from_knots(16.0);
+    const ALPHA_THRESHOLD: Angle = Angle::from_degrees(2.5);
+    const ALTITUDE_THRESHOLD: Length = Length::from_feet(300.0);
+
+    fn disagrees_with(&self, other: &AirData) -> bool {
+        (self.ias - other.ias).abs() > Self::IAS_THRESHOLD
+            || (self.alpha - other.alpha).abs() > Self::ALPHA_THRESHOLD
+            || (self.altitude - other.altitude).abs() > Self::ALTITUDE_THRESHOLD
+    }
+}
+
+// The L:vars a channel fault is injected through: A32NX_ADR_<number>_FAULT and
+// A32NX_ADR_<number>_FAULT_MAGNITUDE
+struct FaultVariables {
+    fault: NamedVariable,
+    magnitude: NamedVariable,
+}
+
+struct AdrChannel {
+    number: u8,
+    fault_variables: Option<FaultVariables>, // Registered in init
+    fault: AdrFault,
+    output: AirData,
+    rejected: bool,
+    disagreement_timer: f64,
+    noise_state: u32,
+}
+
+impl AdrChannel {
+    // A channel has to disagree with the voted value for this long (in seconds) before being rejected
+    const DISAGREEMENT_CONFIRMATION_TIME: f64 = 1.0;
+
+    fn new(number: u8) -> Self {
+        AdrChannel {
+            number,
+            fault_variables: None,
+            fault: AdrFault::None,
+            output: AirData::default(),
+            rejected: false,
+            disagreement_timer: 0.0,
+            noise_state: 0x9E37_79B9 ^ number as u32,
+        }
+    }
+
+    fn init(&mut self) {
+        self.fault_variables = Some(FaultVariables {
+            fault: NamedVariable::from(&format!("A32NX_ADR_{}_FAULT", self.number)),
+            magnitude: NamedVariable::from(&format!("A32NX_ADR_{}_FAULT_MAGNITUDE", self.number)),
+        });
+    }
+
+    fn update(&mut self, sensed: &AirData) {
+        let (fault, magnitude) = match &self.fault_variables {
+            Some(variables) => (
+                AdrFault::from(variables.fault.get_value::<f64>()),
+                variables.magnitude.get_value::<f64>(),
+            ),
+            None => (AdrFault::None, 0.0),
+        };
+        self.inject(sensed, fault, magnitude);
+    }
+
+    // The fault magnitude is expressed as a multiple of the disagreement thresholds, so that a
+    // magnitude above 1.0 will eventually get the channel rejected.
+    fn inject(&mut self, sensed: &AirData, fault: AdrFault, magnitude: f64) {
+        // Clearing the fault restores the channel
+        if fault == AdrFault::None {
+            self.rejected = false;
+            self.disagreement_timer = 0.0;
+        }
+
+        self.output = match fault {
+            AdrFault::None => *sensed,
+            AdrFault::Bias => AirData {
+                ias: sensed.ias + magnitude * AirData::IAS_THRESHOLD,
+                alpha: sensed.alpha + magnitude * AirData::ALPHA_THRESHOLD,
+                altitude: sensed.altitude + magnitude * AirData::ALTITUDE_THRESHOLD,
+            },
+            // Only freeze once, when the fault appears
+            AdrFault::Frozen if self.fault == AdrFault::Frozen => self.output,
+            AdrFault::Frozen => *sensed,
+            AdrFault::Noisy => AirData {
+                ias: sensed.ias + magnitude * AirData::IAS_THRESHOLD * self.noise(),
+                alpha: sensed.alpha + magnitude * AirData::ALPHA_THRESHOLD * self.noise(),
+                altitude: sensed.altitude + magnitude * AirData::ALTITUDE_THRESHOLD * self.noise(),
+            },
+        };
+        self.fault = fault;
+    }
+
+    // Monitors the channel output against the voted value, and rejects it if it keeps disagreeing
+    fn monitor(&mut self, voted: &AirData, dt: f64) {
+        if self.rejected {
+            return;
+        }
+
+        if self.output.disagrees_with(voted) {
+            self.disagreement_timer += dt;
+            if self.disagreement_timer >= Self::DISAGREEMENT_CONFIRMATION_TIME {
+                self.rejected = true;
+            }
+        } else {
+            self.disagreement_timer = 0.0;
+        }
+    }
+
+    // Approximately normally distributed noise in [-1, 1] (sum of uniform xorshift samples)
+    fn noise(&mut self) -> f64 {
+        let mut sum = 0.0;
+        for _ in 0..4 {
+            self.noise_state ^= self.noise_state << 13;
+            self.noise_state ^= self.noise_state >> 17;
+            self.noise_state ^= self.noise_state << 5;
+            sum += self.noise_state as f64 / u32::MAX as f64;
+        }
+        sum / 2.0 - 1.0
+    }
+}
+
+pub(crate) struct AirDataReferences {
+    channels: [AdrChannel; 3],
+    voted: AirData,
+    degraded: bool,
+}
+impl Default for AirDataReferences {
+    fn default() -> Self {
+        AirDataReferences {
+            channels: [AdrChannel::new(1), AdrChannel::new(2), AdrChannel::new(3)],
+            voted: AirData::default(),
+            degraded: false,
+        }
+    }
+}
+
+impl AirDataReferences {
+    pub(crate) fn init(&mut self) {
+        for channel in self.channels.iter_mut() {
+            channel.init();
+        }
+    }
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        let dt = ctx.sim_time.delta();
+
+        // All three channels sense the same sim values, differences only come from injected faults
+        let sensed = AirData {
+            ias: ctx.data.ias(),
+            alpha: ctx.data.alpha(),
+            altitude: ctx.data.altitude(),
+        };
+        for channel in self.channels.iter_mut() {
+            channel.update(&sensed);
+        }
+        self.vote(dt);
+
+        Ok(())
+    }
+
+    // Votes the channel outputs, and monitors the channels against the voted value
+    fn vote(&mut self, dt: f64) {
+        let mut valid = [None; 3];
+        for (output, channel) in valid.iter_mut().zip(self.channels.iter()) {
+            if !channel.rejected {
+                *output = Some(channel.output);
+            }
+        }
+
+        match valid {
+            // With three channels, the median rejects a single erroneous channel
+            [Some(a), Some(b), Some(c)] => {
+                self.voted = AirData {
+                    ias: median(a.ias, b.ias, c.ias),
+                    alpha: median(a.alpha, b.alpha, c.alpha),
+                    altitude: median(a.altitude, b.altitude, c.altitude),
+                };
+                self.degraded = false;
+            }
+            // With two channels, we cannot tell which one is wrong if they disagree
+            [Some(a), Some(b), None] | [Some(a), None, Some(b)] | [None, Some(a), Some(b)] => {
+                self.voted = AirData {
+                    ias: (a.ias + b.ias) / 2.0,
+                    alpha: (a.alpha + b.alpha) / 2.0,
+                    altitude: (a.altitude + b.altitude) / 2.0,
+                };
+                self.degraded = a.disagrees_with(&b);
+            }
+            [Some(a), None, None] | [None, Some(a), None] | [None, None, Some(a)] => {
+                self.voted = a;
+                self.degraded = true;
+            }
+            // Keep the last voted values, there is nothing left to trust
+            [None, None, None] => {
+                self.degraded = true;
+            }
+        }
+
+        // The odd channel out is only identifiable while three channels are still voting
+        if valid.iter().all(Option::is_some) {
+            for channel in self.channels.iter_mut() {
+                channel.monitor(&self.voted, dt);
+            }
+        }
+    }
+
+    // True if fewer than two ADRs can be trusted, which degrades the flight control law
+    pub(crate) fn degraded(&self) -> bool {
+        self.degraded
+    }
+
+    pub(crate) fn alpha(&self) -> Angle {
+        self.voted.alpha
+    }
+
+    pub(crate) fn ias(&self) -> Velocity {
+        self.voted.ias
+    }
+}
+
+fn median<T: PartialOrd + Copy>(a: T, b: T, c: T) -> T {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    if c <= low {
+        low
+    } else if c >= high {
+        high
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.05;
+
+    fn air_data(ias: f64) -> AirData {
+        AirData {
+            ias: Velocity::from_knots(ias),
+            alpha: Angle::from_degrees(3.0),
+            altitude: Length::from_feet(10_000.0),
+        }
+    }
+
+    // Votes the given channel outputs for a number of seconds
+    fn vote(adrs: &mut AirDataReferences, outputs: [AirData; 3], seconds: f64) {
+        for _ in 0..(seconds / DT) as usize {
+            for (channel, &output) in adrs.channels.iter_mut().zip(outputs.iter()) {
+                channel.output = output;
+            }
+            adrs.vote(DT);
+        }
+    }
+
+    #[test]
+    fn median_of_three() {
+        assert_eq!(median(1.0, 2.0, 3.0), 2.0);
+        assert_eq!(median(3.0, 1.0, 2.0), 2.0);
+        assert_eq!(median(2.0, 3.0, 1.0), 2.0);
+        assert_eq!(median(1.0, 1.0, 3.0), 1.0);
+    }
+
+    #[test]
+    fn three_channels_agree() {
+        let mut adrs = AirDataReferences::default();
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(251.0), air_data(252.0)],
+            5.0,
+        );
+
+        assert_eq!(adrs.ias(), Velocity::from_knots(251.0));
+        assert!(!adrs.degraded());
+        assert!(adrs.channels.iter().all(|channel| !channel.rejected));
+    }
+
+    #[test]
+    fn outlier_is_voted_out_then_rejected() {
+        let mut adrs = AirDataReferences::default();
+        let outputs = [air_data(250.0), air_data(300.0), air_data(250.0)];
+
+        // The median ignores the outlier straight away, but it is only rejected once confirmed
+        vote(&mut adrs, outputs, 0.5);
+        assert_eq!(adrs.ias(), Velocity::from_knots(250.0));
+        assert!(!adrs.channels[1].rejected);
+
+        vote(&mut adrs, outputs, 1.0);
+        assert!(adrs.channels[1].rejected);
+        assert!(!adrs.channels[0].rejected && !adrs.channels[2].rejected);
+        // The two remaining channels still agree
+        assert_eq!(adrs.ias(), Velocity::from_knots(250.0));
+        assert!(!adrs.degraded());
+    }
+
+    #[test]
+    fn short_disagreement_is_not_rejected() {
+        let mut adrs = AirDataReferences::default();
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(300.0), air_data(250.0)],
+            0.5,
+        );
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(250.0), air_data(250.0)],
+            0.5,
+        );
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(300.0), air_data(250.0)],
+            0.5,
+        );
+
+        assert!(!adrs.channels[1].rejected);
+    }
+
+    #[test]
+    fn two_failed_channels_degrade() {
+        let mut adrs = AirDataReferences::default();
+        adrs.channels[0].rejected = true;
+        adrs.channels[1].rejected = true;
+        vote(
+            &mut adrs,
+            [air_data(200.0), air_data(300.0), air_data(250.0)],
+            DT,
+        );
+
+        assert_eq!(adrs.ias(), Velocity::from_knots(250.0));
+        assert!(adrs.degraded());
+    }
+
+    #[test]
+    fn two_disagreeing_channels_degrade() {
+        let mut adrs = AirDataReferences::default();
+        adrs.channels[0].rejected = true;
+
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(252.0), air_data(250.0)],
+            DT,
+        );
+        assert!(!adrs.degraded());
+
+        // Neither can be rejected, as there is no third channel to tell which one is wrong
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(300.0), air_data(250.0)],
+            5.0,
+        );
+        assert!(adrs.degraded());
+        assert!(!adrs.channels[1].rejected && !adrs.channels[2].rejected);
+    }
+
+    #[test]
+    fn no_channel_left_keeps_the_last_vote() {
+        let mut adrs = AirDataReferences::default();
+        vote(
+            &mut adrs,
+            [air_data(250.0), air_data(250.0), air_data(250.0)],
+            DT,
+        );
+        for channel in adrs.channels.iter_mut() {
+            channel.rejected = true;
+        }
+        vote(
+            &mut adrs,
+            [air_data(100.0), air_data(100.0), air_data(100.0)],
+            DT,
+        );
+
+        assert_eq!(adrs.ias(), Velocity::from_knots(250.0));
+        assert!(adrs.degraded());
+    }
+
+    #[test]
+    fn injected_faults() {
+        let mut channel = AdrChannel::new(1);
+        let sensed = air_data(250.0);
+
+        channel.inject(&sensed, AdrFault::Bias, 2.0);
+        assert_eq!(channel.output.ias, Velocity::from_knots(282.0));
+        assert_eq!(channel.output.alpha, Angle::from_degrees(8.0));
+
+        // A frozen channel keeps the values it had when the fault appeared
+        channel.inject(&sensed, AdrFault::Frozen, 0.0);
+        channel.inject(&air_data(200.0), AdrFault::Frozen, 0.0);
+        assert_eq!(channel.output.ias, Velocity::from_knots(250.0));
+
+        // A noisy channel stays within the magnitude of the thresholds
+        for _ in 0..100 {
+            channel.inject(&sensed, AdrFault::Noisy, 0.5);
+            assert!((channel.output.ias - sensed.ias).abs() <= AirData::IAS_THRESHOLD * 0.5);
+        }
+
+        // Without its L:vars registered, a channel follows the sensed values
+        channel.update(&air_data(200.0));
+        assert_eq!(channel.output.ias, Velocity::from_knots(200.0));
+    }
+}
@@ -0,0 +1,443 @@
+// Control allocation: splits the roll demand of the laws between the ailerons and the roll
+// spoilers, and adds the aileron droop and the speedbrakes on top.
+//
+// The spoiler panels are numbered 1 (inboard) to 5 (outboard) on each wing, from the FCOM in
+// 1.27.10 under "Description":
+// - Panels 2 to 5 are roll spoilers, up to 35 degrees
+// - Panels 2 to 4 are speedbrakes, up to 40 degrees
+// - All panels are ground spoilers, up to 50 degrees
+// Spoiler deflections are fractions of the 50 degrees full panel travel, from 0.0 (retracted) to
+// 1.0 (fully extended).
+// Aileron deflections are fractions of their 17 degrees travel, positive trailing edge down.
+
+use crate::{
+    clamp, linear_decay_coefficient, linear_range,
+    units::{Angle, Velocity},
+};
+
+pub(crate) const SPOILER_PANELS: usize = 5;
+
+// Which surfaces can be moved, e.g. from their hydraulic supply. The spoilers are powered by the
+// same systems on both wings, so a panel is available on both sides or on neither.
+#[derive(Clone, Copy)]
+pub(crate) struct SurfaceAvailability {
+    pub(crate) ailerons: bool,
+    pub(crate) spoilers: [bool; SPOILER_PANELS],
+}
+
+// The surface positions ordered by the allocation
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub(crate) struct SurfaceDemands {
+    pub(crate) left_aileron: f64,
+    pub(crate) right_aileron: f64,
+    pub(crate) left_spoilers: [f64; SPOILER_PANELS],
+    pub(crate) right_spoilers: [f64; SPOILER_PANELS],
+}
+
+const ROLL_SPOILERS: [bool; SPOILER_PANELS] = [false, true, true, true, true];
+const SPEEDBRAKES: [bool; SPOILER_PANELS] = [false, true, true, true, false];
+
+// Maximum roll spoiler and speedbrake deflections (35 and 40 over 50 degrees), and the ground
+// spoilers extend to the full travel
+const MAX_ROLL_SPOILER: f64 = 0.7;
+const MAX_SPEEDBRAKE: f64 = 0.8;
+const MAX_GROUND_SPOILER: f64 = 1.0;
+
+// The roll spoilers only extend once the roll demand exceeds this, so that the small corrections
+// are flown by the ailerons alone
+const SPOILER_DEAD_BAND: f64 = 0.1;
+
+// The roll spoilers have their full authority up to the first speed, reduced linearly to a part
+// of it at the second, as the ailerons are then effective enough.
+// TODO: Estimated, there is no published schedule
+const FULL_SPOILER_AUTHORITY_SPEED: Velocity = Velocity::from_knots(250.0);
+const REDUCED_SPOILER_AUTHORITY_SPEED: Velocity = Velocity::from_knots(350.0);
+const REDUCED_SPOILER_AUTHORITY: f64 = 0.5;
+
+// Both ailerons droop by 5 degrees with the flaps extended, to increase the lift of the outer
+// wing, from the FCOM in 1.27.10 under "Description". The droop follows the flaps up to their
+// CONF 1+F position of 10 degrees.
+const MAX_AILERON_DROOP: f64 = 5.0 / 17.0;
+const FULL_DROOP_FLAPS_ANGLE: Angle = Angle::from_degrees(10.0);
+
+// The aileron droop for a trailing edge flaps deflection
+pub(crate) fn aileron_droop(flaps_angle: Angle) -> f64 {
+    clamp(
+        flaps_angle.degrees() / FULL_DROOP_FLAPS_ANGLE.degrees(),
+        0.0,
+        1.0,
+    ) * MAX_AILERON_DROOP
+}
+
+// The flight model only has one spoiler per wing, which its speedbrake handle fully extends. The
+// panels of a wing are summed up relative to the full speedbrake extension to position it, so
+// that the speedbrakes have the drag and lift the flight model is tuned for.
+pub(crate) fn flight_model_spoilers(panels: &[f64; SPOILER_PANELS]) -> f64 {
+    let full_speedbrake =
+        SPEEDBRAKES.iter().filter(|&&panel| panel).count() as f64 * MAX_SPEEDBRAKE;
+    clamp(panels.iter().sum::<f64>() / full_speedbrake, 0.0, 1.0)
+}
+
+// Splits a roll demand (-1.0 full left, +1.0 full right) between the ailerons and the roll
+// spoilers, and adds the speedbrakes from their handle position (0.0 retracted, 1.0 full).
+// On the ground, the handle extends every panel as a ground spoiler instead.
+//
+// The roll spoilers extend on the wing going down, and the speedbrakes retract on the wing going
+// up, so that roll authority is kept with the speedbrakes extended.
+// The surfaces which are unavailable are retracted, and their share of the roll demand is taken
+// by the remaining ones.
+// The aileron droop is added to both ailerons, so it has no effect on roll. It gives way to the
+// roll demand near full deflection, so that the down going aileron keeps its full travel.
+pub(crate) fn allocate(
+    roll: f64,
+    droop: f64,
+    speedbrakes: f64,
+    ias: Velocity,
+    on_ground: bool,
+    available: &SurfaceAvailability,
+) -> SurfaceDemands {
+    let roll = clamp(roll, -1.0, 1.0);
+
+    // Without the ailerons the spoilers fly the whole roll demand
+    let (ailerons, spoiler_roll) = if available.ailerons {
+        (
+            roll,
+            clamp(
+                (roll.abs() - SPOILER_DEAD_BAND) / (1.0 - SPOILER_DEAD_BAND),
+                0.0,
+                1.0,
+            ),
+        )
+    } else {
+        (0.0, roll.abs())
+    };
+    let speed_authority = linear_range(
+        linear_decay_coefficient(
+            ias.knots(),
+            FULL_SPOILER_AUTHORITY_SPEED.knots(),
+            REDUCED_SPOILER_AUTHORITY_SPEED.knots(),
+        ),
+        REDUCED_SPOILER_AUTHORITY,
+        1.0,
+    );
+
+    // The roll spoilers which are left share the demand of the unavailable ones
+    let roll_spoilers_available = (0..SPOILER_PANELS)
+        .filter(|&panel| ROLL_SPOILERS[panel] && available.spoilers[panel])
+        .count();
+    let reallocation = if roll_spoilers_available > 0 {
+        ROLL_SPOILERS.iter().filter(|&&panel| panel).count() as f64 / roll_spoilers_available as f64
+    } else {
+        0.0
+    };
+    let spoiler_roll = spoiler_roll * speed_authority * reallocation * MAX_ROLL_SPOILER;
+    let (speedbrake_panels, max_speedbrake) = if on_ground {
+        ([true; SPOILER_PANELS], MAX_GROUND_SPOILER)
+    } else {
+        (SPEEDBRAKES, MAX_SPEEDBRAKE)
+    };
+    let speedbrakes = clamp(speedbrakes, 0.0, 1.0) * max_speedbrake;
+
+    let droop = if available.ailerons {
+        droop.max(0.0).min(1.0 - ailerons.abs())
+    } else {
+        0.0
+    };
+
+    // A right roll lowers the left aileron and raises the right one
+    let mut demands = SurfaceDemands {
+        left_aileron: droop + ailerons,
+        right_aileron: droop - ailerons,
+        ..Default::default()
+    };
+    // A right roll demand extends the right spoilers and retracts the left ones
+    let (down_wing, up_wing) = if roll >= 0.0 {
+        (&mut demands.right_spoilers, &mut demands.left_spoilers)
+    } else {
+        (&mut demands.left_spoilers, &mut demands.right_spoilers)
+    };
+    for panel in 0..SPOILER_PANELS {
+        if !available.spoilers[panel] {
+            continue;
+        }
+        let speedbrake = if speedbrake_panels[panel] {
+            speedbrakes
+        } else {
+            0.0
+        };
+        let roll = if ROLL_SPOILERS[panel] {
+            spoiler_roll.min(MAX_ROLL_SPOILER)
+        } else {
+            0.0
+        };
+        // No panel extends beyond the speedbrake (or ground spoiler) deflection
+        down_wing[panel] = clamp(speedbrake + roll, 0.0, max_speedbrake);
+        up_wing[panel] = clamp(speedbrake - roll, 0.0, max_speedbrake);
+    }
+
+    demands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_close, flight_model, limits::Configuration};
+
+    const ALL_AVAILABLE: SurfaceAvailability = SurfaceAvailability {
+        ailerons: true,
+        spoilers: [true; SPOILER_PANELS],
+    };
+
+    fn knots(knots: f64) -> Velocity {
+        Velocity::from_knots(knots)
+    }
+
+    // The roll allocated to the spoilers, from the right spoilers minus the left ones
+    fn spoiler_roll(demands: &SurfaceDemands, panel: usize) -> f64 {
+        demands.right_spoilers[panel] - demands.left_spoilers[panel]
+    }
+
+    #[test]
+    fn small_roll_demands_are_flown_by_the_ailerons() {
+        let demands = allocate(0.08, 0.0, 0.0, knots(200.0), false, &ALL_AVAILABLE);
+
+        assert_close(demands.left_aileron, 0.08, 1e-12);
+        assert_close(demands.right_aileron, -0.08, 1e-12);
+        assert_eq!(demands.left_spoilers, [0.0; SPOILER_PANELS]);
+        assert_eq!(demands.right_spoilers, [0.0; SPOILER_PANELS]);
+    }
+
+    #[test]
+    fn roll_split() {
+        // (0.55 - 0.1) / 0.9 of the 0.7 roll spoiler travel, on the down going wing
+        let demands = allocate(0.55, 0.0, 0.0, knots(200.0), false, &ALL_AVAILABLE);
+        assert_close(demands.left_aileron, 0.55, 1e-12);
+        assert_close(demands.right_aileron, -0.55, 1e-12);
+        assert_eq!(demands.left_spoilers, [0.0; SPOILER_PANELS]);
+        assert_close(demands.right_spoilers[0], 0.0, 1e-12);
+        for panel in 1..SPOILER_PANELS {
+            assert_close(demands.right_spoilers[panel], 0.35, 1e-12);
+        }
+
+        // And mirrored to the left
+        let left = allocate(-0.55, 0.0, 0.0, knots(200.0), false, &ALL_AVAILABLE);
+        assert_eq!(left.left_spoilers, demands.right_spoilers);
+        assert_eq!(left.right_spoilers, demands.left_spoilers);
+        assert_close(left.left_aileron, -0.55, 1e-12);
+    }
+
+    #[test]
+    fn reallocation_on_panel_loss() {
+        // The three roll spoilers left fly the share of panel 3
+        let available = SurfaceAvailability {
+            spoilers: [true, true, false, true, true],
+            ..ALL_AVAILABLE
+        };
+        let demands = allocate(0.55, 0.0, 0.0, knots(200.0), false, &available);
+        assert_close(demands.right_spoilers[2], 0.0, 1e-12);
+        for panel in [1, 3, 4] {
+            assert_close(demands.right_spoilers[panel], 0.35 * 4.0 / 3.0, 1e-12);
+        }
+
+        // Up to their travel
+        let available = SurfaceAvailability {
+            spoilers: [true, false, false, false, true],
+            ..ALL_AVAILABLE
+        };
+        let demands = allocate(0.55, 0.0, 0.0, knots(200.0), false, &available);
+        assert_close(demands.right_spoilers[4], MAX_ROLL_SPOILER, 1e-12);
+
+        // Without the ailerons, the spoilers fly the whole demand, without dead band
+        let available = SurfaceAvailability {
+            ailerons: false,
+            ..ALL_AVAILABLE
+        };
+        let demands = allocate(0.5, 0.3, 0.0, knots(200.0), false, &available);
+        assert_close(demands.left_aileron, 0.0, 1e-12);
+        assert_close(demands.right_aileron, 0.0, 1e-12);
+        assert_close(spoiler_roll(&demands, 1), 0.35, 1e-12);
+
+        // Without any roll spoiler, nothing extends
+        let available = SurfaceAvailability {
+            spoilers: [true, false, false, false, false],
+            ..ALL_AVAILABLE
+        };
+        let demands = allocate(0.55, 0.0, 0.0, knots(200.0), false, &available);
+        assert_eq!(demands.right_spoilers, [0.0; SPOILER_PANELS]);
+    }
+
+    #[test]
+    fn speed_authority() {
+        let roll = |ias| {
+            spoiler_roll(
+                &allocate(0.55, 0.0, 0.0, knots(ias), false, &ALL_AVAILABLE),
+                1,
+            )
+        };
+
+        assert_close(roll(250.0), 0.35, 1e-12);
+        assert_close(roll(300.0), 0.35 * 0.75, 1e-12);
+        assert_close(roll(350.0), 0.35 * 0.5, 1e-12);
+        assert_close(roll(400.0), 0.35 * 0.5, 1e-12);
+    }
+
+    #[test]
+    fn speedbrakes() {
+        let demands = allocate(0.0, 0.0, 1.0, knots(250.0), false, &ALL_AVAILABLE);
+        assert_eq!(demands.left_spoilers, [0.0, 0.8, 0.8, 0.8, 0.0]);
+        assert_eq!(demands.right_spoilers, demands.left_spoilers);
+
+        // The roll spoilers retract the up going wing, as the down going one is at its limit
+        let demands = allocate(0.55, 0.0, 1.0, knots(250.0), false, &ALL_AVAILABLE);
+        for panel in 1..4 {
+            assert_close(demands.right_spoilers[panel], 0.8, 1e-12);
+            assert_close(demands.left_spoilers[panel], 0.45, 1e-12);
+        }
+        assert_close(demands.right_spoilers[4], 0.35, 1e-12);
+        assert_close(demands.left_spoilers[4], 0.0, 1e-12);
+    }
+
+    #[test]
+    fn ground_spoilers() {
+        let demands = allocate(0.0, 0.0, 1.0, knots(130.0), true, &ALL_AVAILABLE);
+        assert_eq!(demands.left_spoilers, [1.0; SPOILER_PANELS]);
+        assert_eq!(demands.right_spoilers, [1.0; SPOILER_PANELS]);
+
+        let demands = allocate(0.0, 0.0, 0.5, knots(130.0), true, &ALL_AVAILABLE);
+        assert_eq!(demands.left_spoilers, [0.5; SPOILER_PANELS]);
+
+        // An unpowered panel stays retracted
+        let available = SurfaceAvailability {
+            spoilers: [false, true, true, true, true],
+            ..ALL_AVAILABLE
+        };
+        let demands = allocate(0.0, 0.0, 1.0, knots(130.0), true, &available);
+        assert_eq!(demands.left_spoilers, [0.0, 1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn flight_model_spoilers() {
+        let spoilers = |roll, speedbrakes, on_ground| {
+            let demands = allocate(
+                roll,
+                0.0,
+                speedbrakes,
+                knots(200.0),
+                on_ground,
+                &ALL_AVAILABLE,
+            );
+            (
+                super::flight_model_spoilers(&demands.left_spoilers),
+                super::flight_model_spoilers(&demands.right_spoilers),
+            )
+        };
+
+        assert_eq!(spoilers(0.0, 0.0, false), (0.0, 0.0));
+        assert_eq!(spoilers(0.0, 1.0, false), (1.0, 1.0));
+        assert_eq!(spoilers(0.0, 0.5, false), (0.5, 0.5));
+        assert_eq!(spoilers(0.0, 1.0, true), (1.0, 1.0));
+        // Four roll spoilers at 0.35 are 1.4 of the 2.4 speedbrake panels
+        let (left, right) = spoilers(0.55, 0.0, false);
+        assert_close(left, 0.0, 1e-12);
+        assert_close(right, 1.4 / 2.4, 1e-12);
+    }
+
+    #[test]
+    fn aileron_droop() {
+        assert_close(super::aileron_droop(Angle::from_degrees(0.0)), 0.0, 1e-12);
+        assert_close(
+            super::aileron_droop(Angle::from_degrees(5.0)),
+            2.5 / 17.0,
+            1e-12,
+        );
+        assert_close(
+            super::aileron_droop(Angle::from_degrees(10.0)),
+            5.0 / 17.0,
+            1e-12,
+        );
+        assert_close(
+            super::aileron_droop(Angle::from_degrees(40.0)),
+            5.0 / 17.0,
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn neutral_ailerons_in_each_configuration() {
+        // The flaps deflection of each configuration from flight_model.cfg, where the handle
+        // position 1 is CONF 1+F. In CONF 1 the flaps are retracted, as in their auto
+        // retraction position.
+        let flaps = |key| Angle::from_degrees(flight_model::values("FLAPS.0", key).unwrap()[0]);
+        let flaps_angle = |configuration| match configuration {
+            Configuration::Clean => flaps("flaps-position.0"),
+            Configuration::Conf1 => flaps("flaps-position-autoretract.1"),
+            Configuration::Conf1F => flaps("flaps-position.1"),
+            Configuration::Conf2 => flaps("flaps-position.2"),
+            Configuration::Conf3 => flaps("flaps-position.3"),
+            Configuration::Full => flaps("flaps-position.4"),
+        };
+
+        for &(configuration, droop) in &[
+            (Configuration::Clean, 0.0),
+            (Configuration::Conf1, 0.0),
+            (Configuration::Conf1F, 5.0),
+            (Configuration::Conf2, 5.0),
+            (Configuration::Conf3, 5.0),
+            (Configuration::Full, 5.0),
+        ] {
+            let demands = allocate(
+                0.0,
+                super::aileron_droop(flaps_angle(configuration)),
+                0.0,
+                knots(150.0),
+                false,
+                &ALL_AVAILABLE,
+            );
+            let (left, right) = (demands.left_aileron * 17.0, demands.right_aileron * 17.0);
+            assert!(
+                (left - droop).abs() < 1e-9 && (right - droop).abs() < 1e-9,
+                "{:?}: ailerons at {} and {} degrees instead of {}",
+                configuration,
+                left,
+                right,
+                droop
+            );
+        }
+    }
+
+    #[test]
+    fn droop_is_added_to_both_ailerons() {
+        let droop = MAX_AILERON_DROOP;
+        let demands = allocate(0.3, droop, 0.0, knots(150.0), false, &ALL_AVAILABLE);
+
+        assert_close(demands.left_aileron, droop + 0.3, 1e-12);
+        assert_close(demands.right_aileron, droop - 0.3, 1e-12);
+
+        // Without the ailerons there is nothing to droop
+        let available = SurfaceAvailability {
+            ailerons: false,
+            ..ALL_AVAILABLE
+        };
+        let demands = allocate(0.3, droop, 0.0, knots(150.0), false, &available);
+        assert_close(demands.left_aileron, 0.0, 1e-12);
+        assert_close(demands.right_aileron, 0.0, 1e-12);
+    }
+
+    #[test]
+    fn droop_gives_way_to_the_roll_demand() {
+        let droop = MAX_AILERON_DROOP;
+
+        // The down going aileron keeps its full travel
+        let demands = allocate(0.9, droop, 0.0, knots(150.0), false, &ALL_AVAILABLE);
+        assert_close(demands.left_aileron, 1.0, 1e-12);
+        assert_close(demands.right_aileron, 0.1 - 0.9, 1e-12);
+
+        // Down to no droop at full deflection, in both directions
+        for roll in [1.0, -1.0, 1.5] {
+            let demands = allocate(roll, droop, 0.0, knots(150.0), false, &ALL_AVAILABLE);
+            let roll = roll.clamp(-1.0, 1.0);
+            assert_close(demands.left_aileron, roll, 1e-12);
+            assert_close(demands.right_aileron, -roll, 1e-12);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+// International Standard Atmosphere, and the air data computed from the pitot-static pressures.
+// The computations are done in SI units, and only cover the troposphere and the lower
+// stratosphere (up to 20 km), which is all of the A320 envelope.
+//
+// Not every quantity is used by the laws yet
+#![allow(dead_code)]
+
+use crate::units::{Length, Pressure, Temperature, Velocity};
+
+// Sea level standard conditions
+const SEA_LEVEL_PRESSURE: f64 = 101_325.0; // Pa
+const SEA_LEVEL_TEMPERATURE: f64 = 288.15; // K
+const SEA_LEVEL_DENSITY: f64 = 1.225; // kg/m^3
+const SEA_LEVEL_SPEED_OF_SOUND: f64 = 340.294; // m/s
+
+// Tropopause
+const TROPOPAUSE_ALTITUDE: f64 = 11_000.0; // m
+const TROPOPAUSE_TEMPERATURE: f64 = 216.65; // K
+const TROPOPAUSE_PRESSURE: f64 = 22_632.06; // Pa
+
+const LAPSE_RATE: f64 = 0.0065; // K/m, in the troposphere
+const GAS_CONSTANT: f64 = 287.052_87; // J/(kg.K), specific gas constant of dry air
+const GRAVITY: f64 = 9.806_65; // m/s^2
+const HEAT_CAPACITY_RATIO: f64 = 1.4;
+
+// g / (R * L), the exponent of the troposphere pressure law
+fn pressure_exponent() -> f64 {
+    GRAVITY / (GAS_CONSTANT * LAPSE_RATE)
+}
+
+fn speed_of_sound(temperature: Temperature) -> f64 {
+    (HEAT_CAPACITY_RATIO * GAS_CONSTANT * temperature.kelvin()).sqrt()
+}
+
+// The ISA static pressure at a pressure altitude
+pub(crate) fn standard_pressure(altitude: Length) -> Pressure {
+    let h = altitude.meters();
+    Pressure::from_pascals(if h <= TROPOPAUSE_ALTITUDE {
+        SEA_LEVEL_PRESSURE
+            * (1.0 - LAPSE_RATE * h / SEA_LEVEL_TEMPERATURE).powf(pressure_exponent())
+    } else {
+        TROPOPAUSE_PRESSURE
+            * (-GRAVITY * (h - TROPOPAUSE_ALTITUDE) / (GAS_CONSTANT * TROPOPAUSE_TEMPERATURE)).exp()
+    })
+}
+
+// The ISA temperature at a pressure altitude
+pub(crate) fn standard_temperature(altitude: Length) -> Temperature {
+    let h = altitude.meters().min(TROPOPAUSE_ALTITUDE);
+    Temperature::from_kelvin(SEA_LEVEL_TEMPERATURE - LAPSE_RATE * h)
+}
+
+// The altitude at which the ISA static pressure is the given pressure
+pub(crate) fn pressure_altitude(pressure: Pressure) -> Length {
+    let p = pressure.pascals();
+    Length::from_meters(if p >= TROPOPAUSE_PRESSURE {
+        SEA_LEVEL_TEMPERATURE / LAPSE_RATE
+            * (1.0 - (p / SEA_LEVEL_PRESSURE).powf(1.0 / pressure_exponent()))
+    } else {
+        TROPOPAUSE_ALTITUDE
+            + GAS_CONSTANT * TROPOPAUSE_TEMPERATURE / GRAVITY * (TROPOPAUSE_PRESSURE / p).ln()
+    })
+}
+
+// The altitude at which the ISA density is the given density (in kg/m^3)
+fn density_altitude_from(density: f64) -> Length {
+    let tropopause_density = TROPOPAUSE_PRESSURE / (GAS_CONSTANT * TROPOPAUSE_TEMPERATURE);
+    Length::from_meters(if density >= tropopause_density {
+        SEA_LEVEL_TEMPERATURE / LAPSE_RATE
+            * (1.0 - (density / SEA_LEVEL_DENSITY).powf(1.0 / (pressure_exponent() - 1.0)))
+    } else {
+        TROPOPAUSE_ALTITUDE
+            + GAS_CONSTANT * TROPOPAUSE_TEMPERATURE / GRAVITY * (tropopause_density / density).ln()
+    })
+}
+
+// Impact pressure ratio qc / p of an isentropic flow at a mach number (subsonic)
+fn impact_pressure_ratio(mach: f64) -> f64 {
+    (1.0 + 0.2 * mach * mach).powf(3.5) - 1.0
+}
+
+// Mach number of an isentropic flow from its impact pressure ratio qc / p (subsonic)
+fn mach_from_impact_pressure_ratio(ratio: f64) -> f64 {
+    (5.0 * ((ratio.max(0.0) + 1.0).powf(2.0 / 7.0) - 1.0)).sqrt()
+}
+
+// The impact pressure sensed at a calibrated airspeed
+fn impact_pressure_from_cas(cas: Velocity) -> f64 {
+    SEA_LEVEL_PRESSURE * impact_pressure_ratio(cas.meters_per_second() / SEA_LEVEL_SPEED_OF_SOUND)
+}
+
+// The pressure altitude above which a calibrated airspeed limit is more restrictive than a mach
+// limit, e.g. the VMO/MMO crossover altitude.
+pub(crate) fn crossover_altitude(cas: Velocity, mach: f64) -> Length {
+    pressure_altitude(Pressure::from_pascals(
+        impact_pressure_from_cas(cas) / impact_pressure_ratio(mach),
+    ))
+}
+
+// The calibrated airspeed at which the given mach number is reached at a static pressure
+pub(crate) fn cas_from_mach(mach: f64, static_pressure: Pressure) -> Velocity {
+    let impact_pressure = static_pressure.pascals() * impact_pressure_ratio(mach);
+    Velocity::from_meters_per_second(
+        SEA_LEVEL_SPEED_OF_SOUND
+            * mach_from_impact_pressure_ratio(impact_pressure / SEA_LEVEL_PRESSURE),
+    )
+}
+
+// The total pressure sensed by a pitot probe moving at a true airspeed through still air
+pub(crate) fn total_pressure(
+    tas: Velocity,
+    static_pressure: Pressure,
+    temperature: Temperature,
+) -> Pressure {
+    let mach = tas.meters_per_second() / speed_of_sound(temperature);
+    static_pressure * (1.0 + impact_pressure_ratio(mach))
+}
+
+// Air data computed from the static and total pressures and the static air temperature
+#[derive(Default, Clone, Copy, serde::Deserialize)]
+pub(crate) struct AirData {
+    pub(crate) cas: Velocity,             // Calibrated airspeed
+    pub(crate) tas: Velocity,             // True airspeed
+    pub(crate) eas: Velocity,             // Equivalent airspeed
+    pub(crate) mach: f64,                 // Mach number
+    pub(crate) pressure_altitude: Length, // Altitude in the ISA at the static pressure
+    pub(crate) density_altitude: Length,  // Altitude in the ISA at the air density
+}
+
+impl AirData {
+    pub(crate) fn compute(
+        static_pressure: Pressure,
+        total_pressure: Pressure,
+        temperature: Temperature,
+    ) -> AirData {
+        let p = static_pressure.pascals();
+        if p <= 0.0 || temperature.kelvin() <= 0.0 {
+            return AirData::default();
+        }
+        let impact_pressure = (total_pressure.pascals() - p).max(0.0);
+
+        let mach = mach_from_impact_pressure_ratio(impact_pressure / p);
+        let tas = mach * speed_of_sound(temperature);
+        let density = p / (GAS_CONSTANT * temperature.kelvin());
+
+        AirData {
+            cas: Velocity::from_meters_per_second(
+                SEA_LEVEL_SPEED_OF_SOUND
+                    * mach_from_impact_pressure_ratio(impact_pressure / SEA_LEVEL_PRESSURE),
+            ),
+            tas: Velocity::from_meters_per_second(tas),
+            eas: Velocity::from_meters_per_second(tas * (density / SEA_LEVEL_DENSITY).sqrt()),
+            mach,
+            pressure_altitude: pressure_altitude(static_pressure),
+            density_altitude: density_altitude_from(density),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    #[test]
+    fn sea_level() {
+        let sea_level = Length::from_meters(0.0);
+
+        assert_close(standard_pressure(sea_level).pascals(), 101_325.0, 1e-6);
+        assert_close(standard_temperature(sea_level).kelvin(), 288.15, 1e-12);
+        assert_close(
+            speed_of_sound(standard_temperature(sea_level)),
+            340.294,
+            1e-3,
+        );
+        assert_close(
+            pressure_altitude(Pressure::from_pascals(101_325.0)).meters(),
+            0.0,
+            1e-6,
+        );
+        assert_close(density_altitude_from(1.225).meters(), 0.0, 0.01);
+    }
+
+    #[test]
+    fn tropopause() {
+        let tropopause = Length::from_meters(11_000.0);
+
+        assert_close(standard_pressure(tropopause).pascals(), 22_632.0, 0.1);
+        assert_close(standard_temperature(tropopause).kelvin(), 216.65, 1e-9);
+        assert_close(
+            pressure_altitude(Pressure::from_pascals(22_632.06)).meters(),
+            11_000.0,
+            0.1,
+        );
+        assert_close(density_altitude_from(0.36392).meters(), 11_000.0, 1.0);
+
+        // Isothermal above, and continuous across it
+        let above = Length::from_meters(11_000.001);
+        let below = Length::from_meters(10_999.999);
+        assert_close(standard_temperature(above).kelvin(), 216.65, 1e-9);
+        assert_close(
+            standard_pressure(above).pascals(),
+            standard_pressure(below).pascals(),
+            0.1,
+        );
+        assert_close(
+            standard_pressure(Length::from_meters(20_000.0)).pascals(),
+            5474.9,
+            0.1,
+        );
+    }
+
+    #[test]
+    fn pressure_altitude_is_the_inverse_of_the_standard_pressure() {
+        for feet in [-1000.0, 0.0, 10_000.0, 30_000.0, 39_000.0, 45_000.0] {
+            let altitude = Length::from_feet(feet);
+            assert_close(
+                pressure_altitude(standard_pressure(altitude)).feet(),
+                feet,
+                1e-6,
+            );
+        }
+    }
+
+    #[test]
+    fn air_data_at_sea_level() {
+        let tas = Velocity::from_knots(250.0);
+        let static_pressure = Pressure::from_pascals(101_325.0);
+        let temperature = Temperature::from_kelvin(288.15);
+        let air_data = AirData::compute(
+            static_pressure,
+            total_pressure(tas, static_pressure, temperature),
+            temperature,
+        );
+
+        assert_close(air_data.cas.knots(), 250.0, 0.01);
+        assert_close(air_data.tas.knots(), 250.0, 0.01);
+        assert_close(air_data.eas.knots(), 250.0, 0.01);
+        assert_close(air_data.pressure_altitude.feet(), 0.0, 1e-6);
+        assert_close(air_data.density_altitude.feet(), 0.0, 0.1);
+    }
+
+    #[test]
+    fn air_data_at_altitude() {
+        // M0.78 at FL350 in the ISA
+        let altitude = Length::from_feet(35_000.0);
+        let static_pressure = standard_pressure(altitude);
+        let temperature = standard_temperature(altitude);
+        let tas = Velocity::from_meters_per_second(0.78 * speed_of_sound(temperature));
+        let air_data = AirData::compute(
+            static_pressure,
+            total_pressure(tas, static_pressure, temperature),
+            temperature,
+        );
+
+        assert_close(air_data.mach, 0.78, 1e-9);
+        assert_close(air_data.tas.knots(), tas.knots(), 1e-6);
+        assert_close(
+            air_data.cas.knots(),
+            cas_from_mach(0.78, static_pressure).knots(),
+            1e-6,
+        );
+        assert_close(air_data.cas.knots(), 264.4, 0.1);
+        assert_close(air_data.pressure_altitude.feet(), 35_000.0, 1e-6);
+    }
+
+    #[test]
+    fn unavailable_pressures() {
+        let air_data = AirData::compute(
+            Pressure::default(),
+            Pressure::default(),
+            Temperature::default(),
+        );
+
+        assert_close(air_data.mach, 0.0, 1e-12);
+        assert_close(air_data.cas.knots(), 0.0, 1e-12);
+    }
+
+    #[test]
+    fn vmo_mmo_crossover() {
+        let altitude = crossover_altitude(Velocity::from_knots(350.0), 0.82);
+
+        assert_close(altitude.feet(), 24_554.0, 1.0);
+        assert_close(
+            cas_from_mach(0.82, standard_pressure(altitude)).knots(),
+            350.0,
+            1e-6,
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    fbw::FBW,
+    law::ControlLaw,
+    units::{Angle, AngularVelocity},
+    Result,
+};
+
+// Autopilot pitch orders accepted by the normal law
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum PitchCommand {
+    Attitude(Angle), // Pitch attitude
+    LoadFactor(f64), // Load factor in g
+}
+
+// Autopilot roll orders accepted by the normal law
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum RollCommand {
+    BankAngle(Angle),          // Bank angle
+    RollRate(AngularVelocity), // Roll rate
+}
+
+// The autopilot commands the FBW laws instead of moving the surfaces. Guidance computers can
+// provide their orders through the following L:vars:
+// - A32NX_FBW_AP_PITCH_MODE: 0 = flight director, 1 = pitch attitude, 2 = load factor
+// - A32NX_FBW_AP_PITCH_COMMAND: the pitch order, in degrees or g
+// - A32NX_FBW_AP_ROLL_MODE: 0 = flight director, 1 = bank angle, 2 = roll rate
+// - A32NX_FBW_AP_ROLL_COMMAND: the roll order, in degrees or degrees/second
+// In flight director mode, the sim flight director pitch and bank are used as attitude orders.
+#[derive(Clone)]
+pub(crate) struct Autopilot {
+    pub(crate) engaged: bool,
+    pub(crate) pitch: PitchCommand,
+    pub(crate) roll: RollCommand,
+}
+impl Default for Autopilot {
+    fn default() -> Self {
+        Autopilot {
+            engaged: false,
+            pitch: PitchCommand::Attitude(Angle::default()),
+            roll: RollCommand::BankAngle(Angle::default()),
+        }
+    }
+}
+
+impl Autopilot {
+    // Sidestick deflection beyond which the pilot takes over and the autopilot disconnects
+    const TAKEOVER_THRESHOLD: f64 = 0.3;
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        self.engaged = ctx.data.autopilot();
+        if !self.engaged {
+            return Ok(());
+        }
+
+        if Self::disconnect_required(ctx.input.yoke_x, ctx.input.yoke_y, ctx.control_laws.active) {
+            ctx.input.disconnect_autopilot(&ctx.sim)?;
+            self.engaged = false;
+            return Ok(());
+        }
+
+        let fetch = |name| {
+            use msfs::msfs::legacy::*;
+            NamedVariable::from(name).get_value::<f64>()
+        };
+
+        self.pitch = Self::pitch_command(
+            fetch("A32NX_FBW_AP_PITCH_MODE"),
+            fetch("A32NX_FBW_AP_PITCH_COMMAND"),
+            ctx.data.flight_director_pitch(),
+        );
+        self.roll = Self::roll_command(
+            fetch("A32NX_FBW_AP_ROLL_MODE"),
+            fetch("A32NX_FBW_AP_ROLL_COMMAND"),
+            ctx.data.flight_director_bank(),
+        );
+
+        Ok(())
+    }
+
+    // The autopilot is only available in normal law, and is overridden by a sidestick input
+    fn disconnect_required(yoke_x: f64, yoke_y: f64, law: ControlLaw) -> bool {
+        let takeover =
+            yoke_x.abs() > Self::TAKEOVER_THRESHOLD || yoke_y.abs() > Self::TAKEOVER_THRESHOLD;
+        takeover || law != ControlLaw::Normal
+    }
+
+    fn pitch_command(mode: f64, command: f64, flight_director: Angle) -> PitchCommand {
+        match mode as u8 {
+            1 => PitchCommand::Attitude(Angle::from_degrees(command)),
+            2 => PitchCommand::LoadFactor(command),
+            _ => PitchCommand::Attitude(flight_director),
+        }
+    }
+
+    fn roll_command(mode: f64, command: f64, flight_director: Angle) -> RollCommand {
+        match mode as u8 {
+            1 => RollCommand::BankAngle(Angle::from_degrees(command)),
+            2 => RollCommand::RollRate(AngularVelocity::from_degrees_per_second(command)),
+            _ => RollCommand::BankAngle(flight_director),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidestick_takeover_disconnects() {
+        assert!(!Autopilot::disconnect_required(
+            0.0,
+            0.0,
+            ControlLaw::Normal
+        ));
+        assert!(!Autopilot::disconnect_required(
+            0.3,
+            -0.3,
+            ControlLaw::Normal
+        ));
+        assert!(Autopilot::disconnect_required(
+            0.31,
+            0.0,
+            ControlLaw::Normal
+        ));
+        assert!(Autopilot::disconnect_required(
+            -0.31,
+            0.0,
+            ControlLaw::Normal
+        ));
+        assert!(Autopilot::disconnect_required(
+            0.0,
+            0.31,
+            ControlLaw::Normal
+        ));
+        assert!(Autopilot::disconnect_required(
+            0.0,
+            -0.31,
+            ControlLaw::Normal
+        ));
+    }
+
+    #[test]
+    fn law_degradation_disconnects() {
+        assert!(Autopilot::disconnect_required(
+            0.0,
+            0.0,
+            ControlLaw::Alternate
+        ));
+        assert!(Autopilot::disconnect_required(0.0, 0.0, ControlLaw::Direct));
+    }
+
+    #[test]
+    fn pitch_commands() {
+        let flight_director = Angle::from_degrees(4.0);
+        assert_eq!(
+            Autopilot::pitch_command(0.0, 10.0, flight_director),
+            PitchCommand::Attitude(flight_director)
+        );
+        assert_eq!(
+            Autopilot::pitch_command(1.0, 10.0, flight_director),
+            PitchCommand::Attitude(Angle::from_degrees(10.0))
+        );
+        assert_eq!(
+            Autopilot::pitch_command(2.0, 1.3, flight_director),
+            PitchCommand::LoadFactor(1.3)
+        );
+    }
+
+    #[test]
+    fn roll_commands() {
+        let flight_director = Angle::from_degrees(-12.0);
+        assert_eq!(
+            Autopilot::roll_command(0.0, 25.0, flight_director),
+            RollCommand::BankAngle(flight_director)
+        );
+        assert_eq!(
+            Autopilot::roll_command(1.0, 25.0, flight_director),
+            RollCommand::BankAngle(Angle::from_degrees(25.0))
+        );
+        assert_eq!(
+            Autopilot::roll_command(2.0, -3.0, flight_director),
+            RollCommand::RollRate(AngularVelocity::from_degrees_per_second(-3.0))
+        );
+    }
+}
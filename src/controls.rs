@@ -1,13 +1,31 @@
 use crate::{
-    clamp, fbw::FBW, linear_decay_coefficient, linear_range, pid::PIDController,
-    pitch_control::PitchControlMode, Result,
+    actuator::Actuator,
+    allocation::{self, SurfaceAvailability, SPOILER_PANELS},
+    autopilot::{Autopilot, PitchCommand, RollCommand},
+    clamp,
+    fbw::FBW,
+    gain_schedule::{GainSchedule, ScheduledController},
+    hydraulics::Hydraulics,
+    law::ControlLaw,
+    linear_decay_coefficient, linear_range,
+    pid::PIDController,
+    pitch_compensation::PitchCompensation,
+    pitch_control::{PitchControl, PitchControlMode},
+    protections::NormalLawProtections,
+    signal::RateLimiter,
+    surfaces::{self, Surface},
+    units::{Angle, AngularVelocity, Pressure},
+    Result,
 };
 use msfs::{
     sim_connect::{data_definition, SimConnect},
     sys::SIMCONNECT_OBJECT_ID_USER,
 };
 
+// The data definition IDs, which are shared with the SimVars of Data
 const CONTROL_SURFACES: u32 = 0;
+const SPOILERS: u32 = 2;
+const TRIM: u32 = 3;
 
 #[data_definition]
 #[derive(Default, Clone)]
@@ -21,41 +39,428 @@ struct ControlSurfaces {
     #[name = "RUDDER POSITION"]
     #[unit = "Position"]
     rudder: f64,
+    #[name = "STEER INPUT CONTROL"]
+    #[unit = "Percent over 100"]
+    steering: f64,
 }
 
+// The flight model spoilers are only set in flight, so that the sim deploys its ground spoilers
+#[data_definition]
+#[derive(Default, Clone)]
+struct Spoilers {
+    #[name = "SPOILERS LEFT POSITION"]
+    #[unit = "Percent over 100"]
+    left: f64,
+    #[name = "SPOILERS RIGHT POSITION"]
+    #[unit = "Percent over 100"]
+    right: f64,
+}
+
+// The trim is only set once the THS has started from the trim the sim was loaded with
+#[data_definition]
 #[derive(Default, Clone)]
+struct Trim {
+    #[name = "ELEVATOR TRIM POSITION"]
+    #[unit = "Degrees"]
+    ths: f64,
+}
+
 pub(crate) struct Controls {
     surfaces: ControlSurfaces,
-    pitch_controller: PitchController,
-    roll_controller: RollController,
+    spoilers: Spoilers,
+    trim: Trim,
+    left_aileron: Surface,
+    right_aileron: Surface,
+    left_elevator: Surface,
+    right_elevator: Surface,
+    ths: Surface,
+    ths_initialised: bool, // True once the THS has been moved to the sim trim
+    left_spoilers: [Surface; SPOILER_PANELS],
+    right_spoilers: [Surface; SPOILER_PANELS],
+    rudder_actuator: Actuator,
+    aileron_droop: RateLimiter,
+    gain_schedule: GainSchedule,
+    pitch_compensation: Option<PitchCompensation>, // Loaded in init
+    laws: Laws,
+}
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            surfaces: Default::default(),
+            spoilers: Default::default(),
+            trim: Default::default(),
+            left_aileron: surfaces::aileron(
+                "A32NX_FBW_LEFT_AILERON_POSITION",
+                "A32NX_FBW_FAILURE_LEFT_AILERON",
+            ),
+            right_aileron: surfaces::aileron(
+                "A32NX_FBW_RIGHT_AILERON_POSITION",
+                "A32NX_FBW_FAILURE_RIGHT_AILERON",
+            ),
+            left_elevator: surfaces::elevator(
+                "A32NX_FBW_LEFT_ELEVATOR_POSITION",
+                "A32NX_FBW_FAILURE_LEFT_ELEVATOR",
+            ),
+            right_elevator: surfaces::elevator(
+                "A32NX_FBW_RIGHT_ELEVATOR_POSITION",
+                "A32NX_FBW_FAILURE_RIGHT_ELEVATOR",
+            ),
+            ths: surfaces::ths(),
+            ths_initialised: false,
+            left_spoilers: surfaces::spoilers(
+                [
+                    "A32NX_FBW_LEFT_SPOILER_1_POSITION",
+                    "A32NX_FBW_LEFT_SPOILER_2_POSITION",
+                    "A32NX_FBW_LEFT_SPOILER_3_POSITION",
+                    "A32NX_FBW_LEFT_SPOILER_4_POSITION",
+                    "A32NX_FBW_LEFT_SPOILER_5_POSITION",
+                ],
+                [
+                    "A32NX_FBW_FAILURE_LEFT_SPOILER_1",
+                    "A32NX_FBW_FAILURE_LEFT_SPOILER_2",
+                    "A32NX_FBW_FAILURE_LEFT_SPOILER_3",
+                    "A32NX_FBW_FAILURE_LEFT_SPOILER_4",
+                    "A32NX_FBW_FAILURE_LEFT_SPOILER_5",
+                ],
+            ),
+            right_spoilers: surfaces::spoilers(
+                [
+                    "A32NX_FBW_RIGHT_SPOILER_1_POSITION",
+                    "A32NX_FBW_RIGHT_SPOILER_2_POSITION",
+                    "A32NX_FBW_RIGHT_SPOILER_3_POSITION",
+                    "A32NX_FBW_RIGHT_SPOILER_4_POSITION",
+                    "A32NX_FBW_RIGHT_SPOILER_5_POSITION",
+                ],
+                [
+                    "A32NX_FBW_FAILURE_RIGHT_SPOILER_1",
+                    "A32NX_FBW_FAILURE_RIGHT_SPOILER_2",
+                    "A32NX_FBW_FAILURE_RIGHT_SPOILER_3",
+                    "A32NX_FBW_FAILURE_RIGHT_SPOILER_4",
+                    "A32NX_FBW_FAILURE_RIGHT_SPOILER_5",
+                ],
+            ),
+            // 25 degrees/second over 25 degrees
+            rudder_actuator: Actuator::new(-1.0, 1.0, 1.0, 0.05),
+            aileron_droop: RateLimiter::new(Self::AILERON_DROOP_RATE),
+            gain_schedule: Default::default(),
+            pitch_compensation: None,
+            laws: Default::default(),
+        }
+    }
 }
 
 impl Controls {
-    pub(crate) fn init(&self, sim: &SimConnect) -> Result<()> {
+    // The aileron droop moves at 1 degree/second (over 17 degrees), so that it doesn't jump when the
+    // flaps extend or retract
+    const AILERON_DROOP_RATE: f64 = 1.0 / 17.0;
+
+    pub(crate) fn init(&mut self, sim: &SimConnect) -> Result<()> {
         sim.add_data_definition::<ControlSurfaces>(CONTROL_SURFACES)?;
+        sim.add_data_definition::<Spoilers>(SPOILERS)?;
+        sim.add_data_definition::<Trim>(TRIM)?;
+        self.gain_schedule = GainSchedule::load();
+        self.pitch_compensation = Some(PitchCompensation::load()?);
+        self.left_aileron.init();
+        self.right_aileron.init();
+        self.left_elevator.init();
+        self.right_elevator.init();
+        self.ths.init();
+        for spoiler in self
+            .left_spoilers
+            .iter_mut()
+            .chain(self.right_spoilers.iter_mut())
+        {
+            spoiler.init();
+        }
 
         Ok(())
     }
 
     pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
-        if ctx.data.autopilot() {
-            self.surfaces.elevator = ctx.input.yoke_y;
-            self.surfaces.ailerons = ctx.input.yoke_x;
-            self.surfaces.rudder = ctx.input.rudder;
-        } else {
-            self.surfaces.elevator = self.pitch_controller.calculate(self.surfaces.elevator, ctx);
-            self.surfaces.ailerons = self.roll_controller.calculate(self.surfaces.ailerons, ctx);
-            self.surfaces.rudder = ctx.input.rudder; // TODO: yaw FBW
+        let inputs = LawInputs::from_fbw(ctx);
+        self.laws.schedule_gains(&self.gain_schedule, &inputs);
+
+        // The elevator which cancels the pitch moment of the current configuration. The spoilers
+        // are taken from the previous frame, as they are moved after the laws.
+        let pitch_compensation = self.pitch_compensation.map_or(0.0, |compensation| {
+            compensation.elevator(
+                ctx.data.flaps_angle(),
+                ctx.data.gear_extension(),
+                (self.spoilers.left + self.spoilers.right) / 2.0,
+            )
+        });
+        let (pitch, roll) = self
+            .laws
+            .fly(ctx.control_laws.active, pitch_compensation, &inputs);
+        let rudder = ctx.input.rudder; // TODO: yaw FBW
+
+        let dt = ctx.sim_time.delta();
+        let droop = self
+            .aileron_droop
+            .update(allocation::aileron_droop(ctx.data.flaps_angle()), dt);
+
+        // The roll demand is split between the ailerons and the roll spoilers
+        let mut spoilers = [false; SPOILER_PANELS];
+        for (panel, available) in spoilers.iter_mut().enumerate() {
+            *available = ctx.hydraulics.spoiler_available(panel + 1);
+        }
+        let demands = allocation::allocate(
+            roll,
+            droop,
+            ctx.data.speedbrake_handle(),
+            ctx.adr.ias(),
+            ctx.data.on_ground(),
+            &SurfaceAvailability {
+                ailerons: ctx.hydraulics.ailerons_available(),
+                spoilers,
+            },
+        );
+
+        // The laws command the actuators, which move the surfaces within their physical limits
+        let dynamic_pressure = ctx.data.dynamic_pressure();
+        let hydraulics = &ctx.hydraulics;
+        let left_elevator = self.left_elevator.update(
+            pitch,
+            hydraulics.left_elevator_available(),
+            dynamic_pressure,
+            dt,
+        );
+        let right_elevator = self.right_elevator.update(
+            pitch,
+            hydraulics.right_elevator_available(),
+            dynamic_pressure,
+            dt,
+        );
+        let left_aileron = self.left_aileron.update(
+            demands.left_aileron,
+            hydraulics.ailerons_available(),
+            dynamic_pressure,
+            dt,
+        );
+        let right_aileron = self.right_aileron.update(
+            demands.right_aileron,
+            hydraulics.ailerons_available(),
+            dynamic_pressure,
+            dt,
+        );
+        // TODO: Automatic pitch trim, until then the THS follows the sim trim. It starts from the
+        // trim the aircraft was loaded with, once the SimVars have been received.
+        if !self.ths_initialised && ctx.data.elevator_trim_valid() {
+            self.ths.reset(ctx.data.elevator_trim().degrees());
+            self.ths_initialised = true;
+        }
+        if self.ths_initialised {
+            self.ths.update(
+                ctx.data.elevator_trim().degrees(),
+                hydraulics.ths_available(),
+                dynamic_pressure,
+                dt,
+            );
+        }
+        let mut left_spoilers = [0.0; SPOILER_PANELS];
+        let mut right_spoilers = [0.0; SPOILER_PANELS];
+        for (panel, &powered) in spoilers.iter().enumerate() {
+            left_spoilers[panel] = self.left_spoilers[panel].update(
+                demands.left_spoilers[panel],
+                powered,
+                dynamic_pressure,
+                dt,
+            );
+            right_spoilers[panel] = self.right_spoilers[panel].update(
+                demands.right_spoilers[panel],
+                powered,
+                dynamic_pressure,
+                dt,
+            );
         }
 
+        // The flight model only has one elevator and one aileron pair, so it is given the mean of
+        // the surfaces. The aileron droop cancels out, it only changes the lift, which the flight
+        // model already gets from the flaps
+        self.surfaces.elevator = (left_elevator + right_elevator) / 2.0;
+        self.surfaces.ailerons = (left_aileron - right_aileron) / 2.0;
+        self.spoilers.left = allocation::flight_model_spoilers(&left_spoilers);
+        self.spoilers.right = allocation::flight_model_spoilers(&right_spoilers);
+        self.trim.ths = self.ths.position();
+        self.surfaces.rudder = self.rudder_actuator.update(
+            rudder,
+            hydraulics.rudder_available(),
+            dynamic_pressure,
+            dt,
+        );
+
+        self.surfaces.steering = Self::steering(ctx.input.rudder, ctx.data.on_ground(), hydraulics);
+
         ctx.sim.set_data_on_sim_object(
             CONTROL_SURFACES,
             SIMCONNECT_OBJECT_ID_USER,
             &self.surfaces,
         )?;
+        if !ctx.data.on_ground() {
+            ctx.sim
+                .set_data_on_sim_object(SPOILERS, SIMCONNECT_OBJECT_ID_USER, &self.spoilers)?;
+        }
+        if self.ths_initialised {
+            ctx.sim
+                .set_data_on_sim_object(TRIM, SIMCONNECT_OBJECT_ID_USER, &self.trim)?;
+        }
 
         Ok(())
     }
+
+    // The rudder pedals steer the nose wheel on the ground. There is no tiller, so they have
+    // its full authority, which the sim reduces with the ground speed as set in the
+    // [CONTACT_POINTS] section of flight_model.cfg.
+    fn steering(rudder: f64, on_ground: bool, hydraulics: &Hydraulics) -> f64 {
+        if on_ground && hydraulics.nosewheel_steering_available() {
+            rudder
+        } else {
+            0.0
+        }
+    }
+}
+
+// What the laws read from the other components in a frame, so that they can also be flown
+// offline
+#[derive(Clone, Copy)]
+struct LawInputs<'a> {
+    dt: f64,
+    yoke_x: f64,
+    yoke_y: f64,
+    pitch: Angle,
+    pitch_rate: AngularVelocity,
+    roll: Angle,
+    gforce: f64,
+    expected_load_factor: f64,
+    alpha: Angle, // The angle of attack voted by the ADRs
+    alpha_prot: Angle,
+    alpha_max: Angle,
+    flaps: u8,
+    dynamic_pressure: Pressure,
+    mach: f64,
+    pitch_gain_factor: f64,
+    pitch_control: &'a PitchControl,
+    protections: &'a NormalLawProtections,
+    autopilot: &'a Autopilot,
+}
+
+impl<'a> LawInputs<'a> {
+    fn from_fbw(ctx: &'a FBW) -> LawInputs<'a> {
+        LawInputs {
+            dt: ctx.sim_time.delta(),
+            yoke_x: ctx.input.yoke_x,
+            yoke_y: ctx.input.yoke_y,
+            pitch: ctx.data.pitch(),
+            pitch_rate: ctx.data.pitch_rate(),
+            roll: ctx.data.roll(),
+            gforce: ctx.data.gforce(),
+            expected_load_factor: ctx.data.expected_load_factor(),
+            alpha: ctx.adr.alpha(),
+            alpha_prot: ctx.data.alpha_prot(),
+            alpha_max: ctx.data.alpha_max(),
+            flaps: ctx.data.flaps(),
+            dynamic_pressure: ctx.data.dynamic_pressure(),
+            mach: ctx.data.mach(),
+            pitch_gain_factor: ctx.weight_balance.pitch_gain_factor,
+            pitch_control: &ctx.pitch_control,
+            protections: &ctx.normal_law_protections,
+            autopilot: &ctx.autopilot,
+        }
+    }
+}
+
+// The pitch and roll laws, which move the surfaces from their demands of the previous frame
+#[derive(Clone)]
+struct Laws {
+    active: ControlLaw, // The law flown in the previous frame
+    pitch_demand: f64,  // The pitch demand of the laws
+    roll_demand: f64,   // The roll demand of the laws, before its allocation to the surfaces
+    pitch_controller: PitchController,
+    roll_controller: RollController,
+}
+impl Default for Laws {
+    fn default() -> Self {
+        Laws {
+            active: ControlLaw::Normal,
+            pitch_demand: 0.0,
+            roll_demand: 0.0,
+            pitch_controller: Default::default(),
+            roll_controller: Default::default(),
+        }
+    }
+}
+
+impl Laws {
+    fn schedule_gains(&mut self, schedule: &GainSchedule, inputs: &LawInputs) {
+        self.pitch_controller.schedule_gains(schedule, inputs);
+        self.roll_controller.schedule_gains(schedule, inputs);
+    }
+
+    // The pitch and roll demands of the active law, given the elevator which cancels the pitch
+    // moment of the configuration
+    fn fly(&mut self, law: ControlLaw, pitch_compensation: f64, inputs: &LawInputs) -> (f64, f64) {
+        // Tracked in every law, so that the configuration changed while normal law was not
+        // flying is not fed forward in one frame when it resumes
+        let feed_forward = self.pitch_controller.feed_forward(pitch_compensation);
+
+        // The controllers were left flying the aircraft as it was when normal law was lost, so
+        // they take over again from the surfaces where the other law left them
+        if law == ControlLaw::Normal && self.active != ControlLaw::Normal {
+            self.pitch_controller.reset();
+            self.roll_controller.reset();
+        }
+        self.active = law;
+
+        // TODO: Alternate law, until then the reconfigured laws are flown direct
+        if law != ControlLaw::Normal {
+            self.pitch_demand = inputs.yoke_y;
+            self.roll_demand = inputs.yoke_x;
+        } else {
+            self.pitch_demand =
+                self.pitch_controller
+                    .calculate(self.pitch_demand, feed_forward, inputs);
+            self.roll_demand = self.roll_controller.calculate(self.roll_demand, inputs);
+        }
+
+        (self.pitch_demand, self.roll_demand)
+    }
+}
+
+// The pitch law flown in a frame, by order of precedence
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum PitchLaw {
+    Ground,
+    Derotation,
+    AngleOfAttack, // The high angle of attack protection, in both flight and flare modes
+    Autopilot,
+    Flare, // Blended in from the flight mode
+    Flight,
+}
+
+impl PitchLaw {
+    fn select(
+        mode: PitchControlMode,
+        aoa_demand_active: bool,
+        autopilot_engaged: bool,
+        flare_effect: f64,
+    ) -> PitchLaw {
+        match mode {
+            PitchControlMode::Ground => PitchLaw::Ground,
+            PitchControlMode::Derotation => PitchLaw::Derotation,
+            _ if aoa_demand_active => PitchLaw::AngleOfAttack,
+            _ if autopilot_engaged => PitchLaw::Autopilot,
+            _ if flare_effect > 0.0 => PitchLaw::Flare,
+            _ => PitchLaw::Flight,
+        }
+    }
+}
+
+// What a pitch law asks for in a frame. Each controller is stepped at most once per frame, so the
+// protections which fly a pitch rate hand it over instead of stepping the pitch rate controller.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum PitchDemand {
+    Elevator(f64),              // An elevator movement
+    PitchRate(AngularVelocity), // A pitch rate to fly with the pitch rate controller
 }
 
 #[derive(Clone)]
@@ -63,138 +468,326 @@ struct PitchController {
     aoa_controller: PIDController,
     gforce_controller: PIDController,
     pitch_rate_controller: PIDController,
+    pitch_compensation: f64, // The configuration compensation applied in the previous frame
+    law: PitchLaw,           // The law flown in the previous frame
 }
 impl Default for PitchController {
     fn default() -> Self {
         PitchController {
             // AoA error -> elevator handle movement rate
-            aoa_controller: PIDController::new(-2.0, 2.0, 0.002, 0.0, 0.0002),
+            aoa_controller: PIDController::new(-2.0, 2.0, 0.002, 0.0, 0.0002)
+                .with_derivative_filter(0.1),
             // GForce error -> elevator handle movement rate
-            gforce_controller: PIDController::new(-2.0, 2.0, 0.008, 0.008, 0.001),
+            gforce_controller: PIDController::new(-2.0, 2.0, 0.008, 0.008, 0.001)
+                .with_derivative_filter(0.1),
             // Pitch rate error -> elevator handle movement rate
-            pitch_rate_controller: PIDController::new(-2.0, 2.0, 0.01, 0.015, 0.0025),
+            pitch_rate_controller: PIDController::new(-2.0, 2.0, 0.01, 0.015, 0.0025)
+                .with_derivative_filter(0.1),
+            pitch_compensation: 0.0,
+            law: PitchLaw::Ground,
         }
     }
 }
 
 impl PitchController {
-    // Applies load factor limitation protection to a proposed elevator movement
-    fn load_factor_limitation(&mut self, delta_elevator: f64, ctx: &FBW) -> f64 {
-        let dt = ctx.sim_time.delta();
-        if ctx.data.gforce() > ctx.normal_law_protections.max_load_factor {
-            self.gforce_controller.update_anti_windup(
-                ctx.normal_law_protections.max_load_factor - ctx.data.gforce(),
+    // Pitch rate (in degrees/second) commanded per degree of pitch attitude error
+    const ATTITUDE_PITCH_GAIN: f64 = 0.5;
+    // Pitch rate available to the autopilot to capture its target attitude
+    const AUTOPILOT_MAX_PITCH_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(3.0);
+    // Pitch attitude change from the flare attitude at full sidestick deflection
+    const FLARE_PITCH_AUTHORITY: Angle = Angle::from_degrees(10.0);
+    // Pitch rate available to capture the flare attitude
+    const MAX_FLARE_PITCH_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(5.0);
+    // Nose down pitch rate limit after the main gear touchdown
+    const MAX_DEROTATION_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(3.0);
+    // Pitch rate used to correct an attitude beyond the pitch attitude protection
+    const CORRECTIVE_PITCH_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(5.0);
+    // Pitch rate allowed away from the pitch attitude limits
+    const MAX_PITCH_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(30.0);
+
+    // Flies a pitch rate with the pitch rate controller
+    fn pitch_rate_demand(&mut self, pitch_rate: AngularVelocity, inputs: &LawInputs) -> f64 {
+        self.pitch_rate_controller.update(
+            pitch_rate.degrees_per_second(),
+            inputs.pitch_rate.degrees_per_second(),
+            inputs.dt,
+        )
+    }
+
+    fn schedule_gains(&mut self, schedule: &GainSchedule, inputs: &LawInputs) {
+        // The pitch gains are also adjusted for the CG position
+        let scale = |controller| {
+            schedule.scale(
+                controller,
+                inputs.flaps,
+                inputs.dynamic_pressure,
+                inputs.mach,
+            ) * inputs.pitch_gain_factor
+        };
+        self.aoa_controller
+            .set_gain_scale(scale(ScheduledController::AngleOfAttack));
+        self.gforce_controller
+            .set_gain_scale(scale(ScheduledController::LoadFactor));
+        self.pitch_rate_controller
+            .set_gain_scale(scale(ScheduledController::PitchRate));
+    }
+
+    // Flies a demand, with the pitch rate controller if it is a pitch rate
+    fn fly(&mut self, demand: PitchDemand, inputs: &LawInputs) -> f64 {
+        match demand {
+            PitchDemand::Elevator(delta_elevator) => delta_elevator,
+            PitchDemand::PitchRate(pitch_rate) => self.pitch_rate_demand(pitch_rate, inputs),
+        }
+    }
+
+    // Applies load factor limitation protection to a proposed demand
+    fn load_factor_limitation(&mut self, demand: PitchDemand, inputs: &LawInputs) -> PitchDemand {
+        let dt = inputs.dt;
+        if inputs.gforce > inputs.protections.max_load_factor {
+            PitchDemand::Elevator(self.gforce_controller.update(
+                inputs.protections.max_load_factor,
+                inputs.gforce,
                 dt,
-            )
-        } else if ctx.data.gforce() < ctx.normal_law_protections.min_load_factor {
-            self.gforce_controller.update_anti_windup(
-                ctx.normal_law_protections.min_load_factor - ctx.data.gforce(),
+            ))
+        } else if inputs.gforce < inputs.protections.min_load_factor {
+            PitchDemand::Elevator(self.gforce_controller.update(
+                inputs.protections.min_load_factor,
+                inputs.gforce,
                 dt,
-            )
+            ))
         } else {
-            delta_elevator
+            demand
         }
     }
 
     // Applies rules assuming sidestick demands angle of attack
-    fn angle_of_attack_demand(&mut self, ctx: &FBW) -> f64 {
-        let dt = ctx.sim_time.delta();
-        let commanded_aoa = if ctx.input.yoke_y >= 0.0 {
+    fn angle_of_attack_demand(&mut self, inputs: &LawInputs) -> PitchDemand {
+        let dt = inputs.dt;
+        let commanded_aoa = if inputs.yoke_y >= 0.0 {
             // Neutral -> Full Up = AoA proportional range from alpha_prot -> alpha_max
             linear_range(
-                ctx.input.yoke_y,
-                ctx.data.alpha_prot(),
-                ctx.data.alpha_max(),
+                inputs.yoke_y,
+                inputs.alpha_prot.degrees(),
+                inputs.alpha_max.degrees(),
             )
         } else {
             // Neutral -> Full Down = AoA proportional range from alpha_prot -> 0 AoA
-            linear_range(ctx.input.yoke_y, ctx.data.alpha_prot(), 0.0)
+            linear_range(inputs.yoke_y, inputs.alpha_prot.degrees(), 0.0)
         };
-        let mut delta_elevator = self
-            .aoa_controller
-            .update_anti_windup(commanded_aoa - ctx.data.alpha(), dt);
+        let mut demand = PitchDemand::Elevator(self.aoa_controller.update(
+            commanded_aoa,
+            inputs.alpha.degrees(),
+            dt,
+        ));
 
         // Apply protections
-        delta_elevator = self.load_factor_limitation(delta_elevator, ctx);
+        demand = self.load_factor_limitation(demand, inputs);
         // This isn't specified in the FCOM, but the flight model is not true enough to real life.
-        delta_elevator = self.pitch_attitude_protection(delta_elevator, ctx);
+        demand = Self::pitch_attitude_protection(demand, inputs);
 
-        delta_elevator
+        demand
     }
 
-    // Applies pitch attitude protection to a proposed elevator movement
-    fn pitch_attitude_protection(&mut self, delta_elevator: f64, ctx: &FBW) -> f64 {
-        let dt = ctx.sim_time.delta();
-        if ctx.data.pitch() > ctx.normal_law_protections.max_pitch_angle {
+    // Flight mode: the sidestick demands a load factor, and holds the flight path when released
+    fn load_factor_demand(&mut self, inputs: &LawInputs) -> PitchDemand {
+        let dt = inputs.dt;
+        let protections = &inputs.protections;
+        let neutral = inputs.expected_load_factor;
+        let commanded_load_factor = if inputs.yoke_y >= 0.0 {
+            linear_range(inputs.yoke_y, neutral, protections.max_load_factor)
+        } else {
+            linear_range(-inputs.yoke_y, neutral, protections.min_load_factor)
+        };
+        let demand = PitchDemand::Elevator(self.gforce_controller.update(
+            commanded_load_factor,
+            inputs.gforce,
+            dt,
+        ));
+
+        // Apply protections
+        Self::pitch_attitude_protection(demand, inputs)
+    }
+
+    // Flare mode: the sidestick demands a pitch attitude relative to the flare attitude
+    fn flare_pitch_rate(inputs: &LawInputs) -> AngularVelocity {
+        let pitch =
+            inputs.pitch_control.flare_attitude + Self::FLARE_PITCH_AUTHORITY * inputs.yoke_y;
+        Self::attitude_pitch_rate(pitch, Self::MAX_FLARE_PITCH_RATE, inputs)
+    }
+
+    // The pitch rate which captures a pitch attitude, proportional to the error up to
+    // max_pitch_rate
+    fn attitude_pitch_rate(
+        pitch: Angle,
+        max_pitch_rate: AngularVelocity,
+        inputs: &LawInputs,
+    ) -> AngularVelocity {
+        AngularVelocity::from_degrees_per_second(
+            Self::ATTITUDE_PITCH_GAIN * (pitch - inputs.pitch).degrees(),
+        )
+        .clamp(-max_pitch_rate, max_pitch_rate)
+    }
+
+    // Flies the autopilot pitch command, with the same protections as the sidestick orders
+    fn autopilot_demand(&mut self, inputs: &LawInputs) -> PitchDemand {
+        let dt = inputs.dt;
+        let demand = match inputs
+            .protections
+            .limit_pitch_command(inputs.autopilot.pitch)
+        {
+            PitchCommand::Attitude(pitch) => {
+                // Capture the target pitch attitude using up to +/-3 degrees/second pitch rate
+                let demand = PitchDemand::PitchRate(Self::attitude_pitch_rate(
+                    pitch,
+                    Self::AUTOPILOT_MAX_PITCH_RATE,
+                    inputs,
+                ));
+                self.load_factor_limitation(demand, inputs)
+            }
+            // The command is already bounded to the load factor limits, which the load factor
+            // limitation would fly with the same controller
+            PitchCommand::LoadFactor(load_factor) => PitchDemand::Elevator(
+                self.gforce_controller
+                    .update(load_factor, inputs.gforce, dt),
+            ),
+        };
+
+        // Apply protections
+        Self::pitch_attitude_protection(demand, inputs)
+    }
+
+    // Derotation: the pitch is direct, but the nose is lowered no faster than the derotation rate
+    fn derotation_demand(elevator: f64, yoke_y: f64, pitch_rate: AngularVelocity) -> PitchDemand {
+        if pitch_rate < -Self::MAX_DEROTATION_RATE && yoke_y <= elevator {
+            PitchDemand::PitchRate(-Self::MAX_DEROTATION_RATE)
+        } else {
+            PitchDemand::Elevator(yoke_y - elevator)
+        }
+    }
+
+    // Applies pitch attitude protection to a proposed demand
+    fn pitch_attitude_protection(demand: PitchDemand, inputs: &LawInputs) -> PitchDemand {
+        let pitch = inputs.pitch;
+        let pitch_rate = inputs.pitch_rate;
+        let max_pitch_angle = inputs.protections.max_pitch_angle;
+        let min_pitch_angle = inputs.protections.min_pitch_angle;
+        let one_degree = Angle::from_degrees(1.0);
+
+        if pitch > max_pitch_angle {
             // Correct using up to -5 degrees/second pitch rate when we are up to 1 degree above our limit
             // Thereafter, correct using -5 degrees/second pitch rate
-            let corrective_pitch_rate = -5.0
+            let corrective_pitch_rate = -Self::CORRECTIVE_PITCH_RATE
                 * linear_decay_coefficient(
-                    ctx.data.pitch(),
-                    ctx.normal_law_protections.max_pitch_angle + 1.0,
-                    ctx.normal_law_protections.max_pitch_angle,
+                    pitch.degrees(),
+                    (max_pitch_angle + one_degree).degrees(),
+                    max_pitch_angle.degrees(),
                 );
-            return self
-                .pitch_rate_controller
-                .update_anti_windup(corrective_pitch_rate - ctx.data.pitch_rate(ctx), dt);
+            return PitchDemand::PitchRate(corrective_pitch_rate);
         }
 
-        if ctx.data.pitch() < ctx.normal_law_protections.min_pitch_angle {
+        if pitch < min_pitch_angle {
             // Correct using up to +5 degrees/second pitch rate when we are up to 1 degree above our limit
             // Thereafter, correct using +5 degrees/second pitch rate
-            let corrective_pitch_rate = 5.0
+            let corrective_pitch_rate = Self::CORRECTIVE_PITCH_RATE
                 * linear_decay_coefficient(
-                    ctx.data.pitch(),
-                    ctx.normal_law_protections.min_pitch_angle - 1.0,
-                    ctx.normal_law_protections.min_pitch_angle,
+                    pitch.degrees(),
+                    (min_pitch_angle - one_degree).degrees(),
+                    min_pitch_angle.degrees(),
                 );
-            return self
-                .pitch_rate_controller
-                .update_anti_windup(corrective_pitch_rate - ctx.data.pitch_rate(ctx), dt);
+            return PitchDemand::PitchRate(corrective_pitch_rate);
         }
 
+        // Whether the demand pitches up or down
+        let direction = match demand {
+            PitchDemand::Elevator(delta_elevator) => delta_elevator,
+            PitchDemand::PitchRate(pitch_rate) => pitch_rate.degrees_per_second(),
+        };
+
         // Naturally limit the pitch up/down rate from +/-30 degree/sec to 0 as we approach our limits
-        let max_pitch_rate = 30.0
-            * linear_decay_coefficient(
-                ctx.data.pitch(),
-                0.0,
-                ctx.normal_law_protections.max_pitch_angle,
-            );
-        if ctx.data.pitch_rate(ctx) > max_pitch_rate && delta_elevator >= 0.0 {
-            return self
-                .pitch_rate_controller
-                .update_anti_windup(max_pitch_rate - ctx.data.pitch_rate(ctx), dt);
+        let max_pitch_rate = Self::MAX_PITCH_RATE
+            * linear_decay_coefficient(pitch.degrees(), 0.0, max_pitch_angle.degrees());
+        if pitch_rate > max_pitch_rate && direction >= 0.0 {
+            return PitchDemand::PitchRate(max_pitch_rate);
         }
 
-        let min_pitch_rate = -30.0
-            * linear_decay_coefficient(
-                ctx.data.pitch(),
-                0.0,
-                ctx.normal_law_protections.min_pitch_angle,
-            );
-        if ctx.data.pitch_rate(ctx) < min_pitch_rate && delta_elevator <= 0.0 {
-            return self
-                .pitch_rate_controller
-                .update_anti_windup(min_pitch_rate - ctx.data.pitch_rate(ctx), dt);
-        }
-
-        delta_elevator
-    }
-
-    fn calculate(&mut self, elevator: f64, ctx: &FBW) -> f64 {
-        // On the ground, pitch is direct
-        // TODO: Add ground mode calculations (e.g. when aircraft reaches 70 knots during the T/O roll, maximum deflection of elevators is affected)
-        let new_elevator = if ctx.pitch_control.mode == PitchControlMode::Ground {
-            ctx.input.yoke_y
-        } else if ctx.normal_law_protections.aoa_demand_active {
-            // AoA protections are available in both flight/flare modes
-            elevator + self.angle_of_attack_demand(ctx)
-        } else if ctx.pitch_control.flare_effect > 0.0 {
-            // Flare mode has a special effect and does not have all the protections of flight mode
-            42.0
-        } else {
-            // Flight mode
-            43.0
+        let min_pitch_rate = -Self::MAX_PITCH_RATE
+            * linear_decay_coefficient(pitch.degrees(), 0.0, min_pitch_angle.degrees());
+        if pitch_rate < min_pitch_rate && direction <= 0.0 {
+            return PitchDemand::PitchRate(min_pitch_rate);
+        }
+
+        demand
+    }
+
+    // Clears the controllers, for the law to take over the elevator again
+    fn reset(&mut self) {
+        self.aoa_controller.reset();
+        self.gforce_controller.reset();
+        self.pitch_rate_controller.reset();
+        self.law = PitchLaw::Ground;
+    }
+
+    // The laws move the elevator incrementally, so a configuration change is fed forward as the
+    // change of the elevator which cancels its pitch moment since the previous frame
+    fn feed_forward(&mut self, pitch_compensation: f64) -> f64 {
+        let feed_forward = pitch_compensation - self.pitch_compensation;
+        self.pitch_compensation = pitch_compensation;
+        feed_forward
+    }
+
+    // Moves the elevator from its previous position, adding the configuration feed forward
+    fn calculate(&mut self, elevator: f64, feed_forward: f64, inputs: &LawInputs) -> f64 {
+        let law = PitchLaw::select(
+            inputs.pitch_control.mode,
+            inputs.protections.aoa_demand_active,
+            inputs.autopilot.engaged,
+            inputs.pitch_control.flare_effect,
+        );
+        // The pitch rate controller is taken over after the flare, which left it flying a
+        // different pitch rate
+        if law == PitchLaw::Derotation && self.law != PitchLaw::Derotation {
+            self.pitch_rate_controller.reset();
+        }
+        self.law = law;
+
+        let new_elevator = match law {
+            // On the ground, pitch is direct
+            // TODO: Add ground mode calculations (e.g. when aircraft reaches 70 knots during the T/O roll, maximum deflection of elevators is affected)
+            PitchLaw::Ground => inputs.yoke_y,
+            PitchLaw::Derotation => {
+                let demand = Self::derotation_demand(elevator, inputs.yoke_y, inputs.pitch_rate);
+                elevator + self.fly(demand, inputs)
+            }
+            PitchLaw::AngleOfAttack => {
+                let demand = self.angle_of_attack_demand(inputs);
+                elevator + feed_forward + self.fly(demand, inputs)
+            }
+            PitchLaw::Autopilot => {
+                let demand = self.autopilot_demand(inputs);
+                elevator + feed_forward + self.fly(demand, inputs)
+            }
+            // Flare mode does not have all of the protections of flight mode. When the flight mode
+            // also flies a pitch rate, the rates are blended so that the pitch rate controller is
+            // only stepped once.
+            PitchLaw::Flare => {
+                let flare_effect = inputs.pitch_control.flare_effect;
+                let flare = Self::flare_pitch_rate(inputs);
+                let delta_elevator = match self.load_factor_demand(inputs) {
+                    PitchDemand::Elevator(flight) => {
+                        let flare = self.pitch_rate_demand(flare, inputs);
+                        linear_range(flare_effect, flight, flare)
+                    }
+                    PitchDemand::PitchRate(flight) => {
+                        let pitch_rate = flight + (flare - flight) * flare_effect;
+                        self.pitch_rate_demand(pitch_rate, inputs)
+                    }
+                };
+                elevator + feed_forward + delta_elevator
+            }
+            PitchLaw::Flight => {
+                let demand = self.load_factor_demand(inputs);
+                elevator + feed_forward + self.fly(demand, inputs)
+            }
         };
 
         clamp(new_elevator, -1.0, 1.0)
@@ -203,43 +796,600 @@ impl PitchController {
 
 #[derive(Clone)]
 struct RollController {
-    roll: f64, // The desired bank angle
+    roll: Angle,                // The desired bank angle
+    engaged: bool,              // True if the roll law is flying the ailerons
+    flight_effect: RateLimiter, // Blends from the direct law (0.0) to the roll law (1.0)
     controller: PIDController,
 }
 impl Default for RollController {
     fn default() -> Self {
         RollController {
-            roll: 0.0,
-            controller: PIDController::new(-1.0, 1.0, 0.10, 0.0, 0.02),
+            roll: Angle::default(),
+            engaged: false,
+            flight_effect: RateLimiter::asymmetric(
+                1.0 / Self::LIFTOFF_BLEND_TIME,
+                1.0 / Self::TOUCHDOWN_BLEND_TIME,
+            ),
+            controller: PIDController::new(-1.0, 1.0, 0.10, 0.0, 0.02)
+                .with_derivative_filter(0.1)
+                .with_output_rate_limit(2.0),
         }
     }
 }
 
 impl RollController {
-    fn calculate(&mut self, ailerons: f64, ctx: &FBW) -> f64 {
+    // Roll rate at maximum sidestick deflection
+    const MAX_ROLL_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(15.0);
+    // Roll rate back to the nominal bank angle when the sidestick is released
+    const ROLL_BACK_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(5.0);
+    // Time to blend in the roll law after liftoff, and back to the direct law after touchdown
+    const LIFTOFF_BLEND_TIME: f64 = 5.0;
+    const TOUCHDOWN_BLEND_TIME: f64 = 0.5;
+
+    fn schedule_gains(&mut self, schedule: &GainSchedule, inputs: &LawInputs) {
+        self.controller.set_gain_scale(schedule.scale(
+            ScheduledController::Roll,
+            inputs.flaps,
+            inputs.dynamic_pressure,
+            inputs.mach,
+        ));
+    }
+
+    // Disengages the roll law, which takes over again by holding the bank angle of its next
+    // frame, from the ailerons where they are
+    fn reset(&mut self) {
+        self.engaged = false;
+        self.controller.reset();
+    }
+
+    fn calculate(&mut self, ailerons: f64, inputs: &LawInputs) -> f64 {
         // TODO: Handle other control laws besides normal law
-        let dt = ctx.sim_time.delta();
-        match ctx.pitch_control.mode {
-            PitchControlMode::Flight | PitchControlMode::Flare => {
-                if ctx.input.yoke_x == 0.0 {
-                    // If we are banked beyond the nominal bank angle, roll back to the nominal bank angle
-                    if self.roll.abs() > ctx.normal_law_protections.nominal_bank_angle {
-                        self.roll += 5.0 * -self.roll.signum() * dt;
-                        if self.roll.abs() < ctx.normal_law_protections.nominal_bank_angle {
-                            self.roll =
-                                self.roll.signum() * ctx.normal_law_protections.nominal_bank_angle;
-                        }
-                    }
-                // We should be holding the specified roll angle
+        let dt = inputs.dt;
+
+        // On the ground the roll is direct, and the roll law is blended in after liftoff
+        let direct = inputs.yoke_x;
+        let on_ground = matches!(
+            inputs.pitch_control.mode,
+            PitchControlMode::Ground | PitchControlMode::Derotation
+        );
+        let flight_effect = self
+            .flight_effect
+            .update(if on_ground { 0.0 } else { 1.0 }, dt);
+        if flight_effect <= 0.0 {
+            self.engaged = false;
+            return direct;
+        }
+
+        if !self.engaged {
+            // Take over from direct control by holding the current bank angle, without
+            // a jump in the aileron position
+            self.roll = inputs.roll;
+            self.controller
+                .initialise(ailerons, self.roll.degrees(), inputs.roll.degrees());
+            self.engaged = true;
+        }
+
+        if inputs.autopilot.engaged {
+            match inputs.protections.limit_roll_command(inputs.autopilot.roll) {
+                RollCommand::BankAngle(bank) => self.roll = bank,
+                RollCommand::RollRate(rate) => {
+                    self.roll = (self.roll + Angle::swept(rate, dt)).clamp(
+                        -inputs.protections.max_bank_angle,
+                        inputs.protections.max_bank_angle,
+                    );
+                }
+            }
+        } else if inputs.yoke_x == 0.0 {
+            // If we are banked beyond the nominal bank angle, roll back to the nominal bank angle
+            if self.roll.abs() > inputs.protections.nominal_bank_angle {
+                self.roll -= Angle::swept(Self::ROLL_BACK_RATE, dt) * self.roll.signum();
+                if self.roll.abs() < inputs.protections.nominal_bank_angle {
+                    self.roll = self.roll.signum() * inputs.protections.nominal_bank_angle;
+                }
+            }
+        // We should be holding the specified roll angle
+        } else {
+            // We should be responsive to the user's roll request
+            self.roll += Angle::swept(Self::MAX_ROLL_RATE * inputs.yoke_x, dt);
+            self.roll = self.roll.clamp(
+                -inputs.protections.max_bank_angle,
+                inputs.protections.max_bank_angle,
+            );
+        }
+        let law = self
+            .controller
+            .update(self.roll.degrees(), inputs.roll.degrees(), dt);
+
+        linear_range(flight_effect, direct, law)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assert_close, flight_model, flight_phase::FlightPhase, pitch_control::PitchControlInputs,
+        units::Length,
+    };
+
+    const DT: f64 = 0.02;
+
+    // The clean configuration protections
+    fn protections() -> NormalLawProtections {
+        NormalLawProtections {
+            max_bank_angle: Angle::from_degrees(67.0),
+            nominal_bank_angle: Angle::from_degrees(33.0),
+            min_load_factor: -1.0,
+            max_load_factor: 2.5,
+            min_pitch_angle: Angle::from_degrees(-15.0),
+            max_pitch_angle: Angle::from_degrees(30.0),
+            ..Default::default()
+        }
+    }
+
+    fn pitch_control(mode: PitchControlMode) -> PitchControl {
+        let mut pitch_control = PitchControl::default();
+        pitch_control.mode = mode;
+        pitch_control
+    }
+
+    // Level flight in the clean configuration, with the sidestick released
+    fn level<'a>(
+        pitch_control: &'a PitchControl,
+        protections: &'a NormalLawProtections,
+        autopilot: &'a Autopilot,
+    ) -> LawInputs<'a> {
+        LawInputs {
+            dt: DT,
+            yoke_x: 0.0,
+            yoke_y: 0.0,
+            pitch: Angle::from_degrees(2.5),
+            pitch_rate: AngularVelocity::default(),
+            roll: Angle::default(),
+            gforce: 1.0,
+            expected_load_factor: 1.0,
+            alpha: Angle::from_degrees(2.5),
+            alpha_prot: Angle::from_degrees(9.0),
+            alpha_max: Angle::from_degrees(12.0),
+            flaps: 0,
+            dynamic_pressure: Pressure::from_pounds_per_square_foot(200.0),
+            mach: 0.4,
+            pitch_gain_factor: 1.0,
+            pitch_control,
+            protections,
+            autopilot,
+        }
+    }
+
+    #[test]
+    fn autopilot_is_flown_by_the_normal_law_in_flight() {
+        let select = PitchLaw::select;
+
+        assert_eq!(
+            select(PitchControlMode::Flight, false, true, 0.0),
+            PitchLaw::Autopilot
+        );
+        assert_eq!(
+            select(PitchControlMode::Flare, false, true, 1.0),
+            PitchLaw::Autopilot
+        );
+        // The high angle of attack protection has precedence over the autopilot
+        assert_eq!(
+            select(PitchControlMode::Flight, true, true, 0.0),
+            PitchLaw::AngleOfAttack
+        );
+        // On the ground the sidestick is direct
+        assert_eq!(
+            select(PitchControlMode::Ground, false, true, 0.0),
+            PitchLaw::Ground
+        );
+        assert_eq!(
+            select(PitchControlMode::Derotation, true, true, 0.0),
+            PitchLaw::Derotation
+        );
+    }
+
+    #[test]
+    fn pitch_law_follows_the_pitch_mode() {
+        assert_eq!(
+            PitchLaw::select(PitchControlMode::Flight, false, false, 0.0),
+            PitchLaw::Flight
+        );
+        // The flare is blended in and out, whichever the mode
+        assert_eq!(
+            PitchLaw::select(PitchControlMode::Flight, false, false, 0.5),
+            PitchLaw::Flare
+        );
+        assert_eq!(
+            PitchLaw::select(PitchControlMode::Flare, false, false, 0.5),
+            PitchLaw::Flare
+        );
+    }
+
+    #[test]
+    fn derotation_rate_limit() {
+        let rate = AngularVelocity::from_degrees_per_second;
+        let demand = PitchController::derotation_demand;
+
+        // Lowering the nose faster than 3 degrees per second
+        assert_eq!(
+            demand(0.2, -0.5, rate(-5.0)),
+            PitchDemand::PitchRate(rate(-3.0))
+        );
+        // Also once the elevator has reached the sidestick position
+        assert_eq!(
+            demand(-0.5, -0.5, rate(-5.0)),
+            PitchDemand::PitchRate(rate(-3.0))
+        );
+        // Within the rate limit the sidestick is direct
+        assert_eq!(demand(0.2, -0.5, rate(-2.0)), PitchDemand::Elevator(-0.7));
+        // Pulling up always is
+        assert_eq!(demand(0.2, 0.5, rate(-5.0)), PitchDemand::Elevator(0.3));
+    }
+
+    // A landing flown through the pitch control modes and the pitch law. Once the main gear is on
+    // the ground the aircraft pitches at a rate which follows the elevator, until the nose gear
+    // is on the ground.
+    struct Landing {
+        pitch_control: PitchControl,
+        laws: Laws,
+        pitch: Angle,
+        pitch_rate: AngularVelocity,
+        time: f64, // Since the main gear touchdown
+    }
+
+    impl Landing {
+        // Pitch rate at full elevator deflection, and the time it takes to respond
+        const ELEVATOR_PITCH_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(10.0);
+        const RESPONSE_TIME: f64 = 0.3;
+
+        fn flaring() -> Landing {
+            let mut landing = Landing {
+                pitch_control: PitchControl::default(),
+                laws: Laws::default(),
+                pitch: Angle::from_degrees(5.0),
+                pitch_rate: AngularVelocity::default(),
+                time: 0.0,
+            };
+            for _ in 0..50 {
+                landing.frame(FlightPhase::Flare, false, 0.0);
+            }
+            assert_eq!(landing.pitch_control.mode, PitchControlMode::Flare);
+            landing
+        }
+
+        fn nose_gear_on_ground(&self) -> bool {
+            self.pitch <= Angle::default()
+        }
+
+        // Flies a frame, returning the elevator
+        fn frame(&mut self, phase: FlightPhase, on_ground: bool, yoke_y: f64) -> f64 {
+            let nose_gear_on_ground = on_ground && self.nose_gear_on_ground();
+            self.pitch_control.process(&PitchControlInputs {
+                dt: DT,
+                phase,
+                on_ground,
+                nose_gear_on_ground,
+                pitch: self.pitch,
+                radio_height: Length::from_feet(if on_ground { 0.0 } else { 20.0 }),
+            });
+            let (protections, autopilot) = (protections(), Autopilot::default());
+            let inputs = LawInputs {
+                yoke_y,
+                pitch: self.pitch,
+                pitch_rate: self.pitch_rate,
+                ..level(&self.pitch_control, &protections, &autopilot)
+            };
+            let (elevator, _) = self.laws.fly(ControlLaw::Normal, 0.0, &inputs);
+
+            if on_ground {
+                self.time += DT;
+                if nose_gear_on_ground {
+                    self.pitch_rate = AngularVelocity::default();
                 } else {
-                    // We should be responsive to the user's roll request
-                    self.roll += 15.0 * ctx.input.yoke_x * dt; // 15 degrees/sec at maximum deflection
-                    self.roll = clamp(self.roll, 0.0, 1.0);
+                    self.pitch_rate += (Self::ELEVATOR_PITCH_RATE * elevator - self.pitch_rate)
+                        * (DT / Self::RESPONSE_TIME);
+                    self.pitch =
+                        (self.pitch + Angle::swept(self.pitch_rate, DT)).max(Angle::default());
                 }
-                self.controller
-                    .update_anti_windup(self.roll - ctx.data.roll(), dt)
             }
-            _ => ailerons,
+            elevator
         }
     }
+
+    #[test]
+    fn scripted_derotation() {
+        let mut landing = Landing::flaring();
+
+        // Main gear touchdown, the rollout is confirmed after 0.5 s
+        while landing.time < 0.5 {
+            landing.frame(FlightPhase::Flare, true, 0.0);
+            assert_eq!(landing.pitch_control.mode, PitchControlMode::Derotation);
+        }
+
+        // The pilot pushes for 5 degrees per second nose down. The elevator is eased off whenever
+        // the nose comes down faster than 3 degrees per second, which the aircraft follows with
+        // its response time.
+        let (mut fastest, mut eased_off) = (AngularVelocity::default(), false);
+        while !landing.nose_gear_on_ground() {
+            let elevator = landing.frame(FlightPhase::Rollout, true, -0.5);
+            assert_eq!(landing.pitch_control.mode, PitchControlMode::Derotation);
+            assert!(landing.time < 5.0);
+            fastest = fastest.min(landing.pitch_rate);
+            eased_off |= elevator > -0.3;
+        }
+        assert!(eased_off);
+        assert!(fastest > AngularVelocity::from_degrees_per_second(-4.0));
+
+        // Nose gear touchdown, the pitch is direct again
+        assert_eq!(landing.frame(FlightPhase::Rollout, true, -0.5), -0.5);
+        assert_eq!(landing.pitch_control.mode, PitchControlMode::Ground);
+        assert_eq!(landing.frame(FlightPhase::Rollout, true, 0.2), 0.2);
+    }
+
+    #[test]
+    fn scripted_derotation_times_out() {
+        let mut landing = Landing::flaring();
+
+        // The nose is held up after the main gear touchdown, until ground mode is confirmed
+        while landing.time < 4.9 {
+            landing.frame(FlightPhase::Rollout, true, 0.0);
+            assert_eq!(landing.pitch_control.mode, PitchControlMode::Derotation);
+        }
+        assert!(!landing.nose_gear_on_ground());
+        for _ in 0..10 {
+            landing.frame(FlightPhase::Rollout, true, 0.1);
+        }
+        assert_eq!(landing.pitch_control.mode, PitchControlMode::Ground);
+        assert_eq!(landing.frame(FlightPhase::Rollout, true, 0.1), 0.1);
+    }
+
+    #[test]
+    fn configuration_feed_forward() {
+        let mut controller = PitchController::default();
+
+        // Extending the flaps is fed forward once, as the change of its compensation
+        assert_close(controller.feed_forward(0.1), 0.1, 1e-12);
+        assert_close(controller.feed_forward(0.1), 0.0, 1e-12);
+        assert_close(controller.feed_forward(0.3), 0.2, 1e-12);
+        // Retracting them is fed backward
+        assert_close(controller.feed_forward(0.25), -0.05, 1e-12);
+        assert_close(controller.feed_forward(0.0), -0.25, 1e-12);
+    }
+
+    #[test]
+    fn feed_forward_tracks_the_configuration_across_frames() {
+        let mut controller = PitchController::default();
+
+        // The flaps extended over several frames are fed forward frame by frame, and add up to
+        // their compensation however many frames normal law did not fly
+        let fed_forward: f64 = [0.05, 0.1, 0.15, 0.2, 0.2]
+            .iter()
+            .map(|&compensation| controller.feed_forward(compensation))
+            .sum();
+        assert_close(fed_forward, 0.2, 1e-12);
+        assert_close(controller.feed_forward(0.2), 0.0, 1e-12);
+    }
+
+    // The pitch axis of the aircraft at 200 psf, as a short period model of the deviations from
+    // its trim. The flaps and the elevator moments are those of flight_model.cfg, and the
+    // stability and the damping those of a transport aircraft.
+    struct PitchModel {
+        flaps_moment: f64,    // Pitch moment coefficient with the flaps fully extended
+        elevator_moment: f64, // Pitch moment coefficient per radian of elevator, trailing edge down
+        elevator_up_limit: Angle,
+        elevator_down_limit: Angle,
+        full_flaps_angle: Angle,
+        elevator: Surface,
+        alpha: f64,      // In radians
+        pitch_rate: f64, // In radians per second
+        pitch: f64,      // In radians
+    }
+
+    impl PitchModel {
+        // Pitch acceleration per unit of pitch moment coefficient, from the dynamic pressure,
+        // the wing area, its mean chord and the pitch moment of inertia
+        const MOMENT_ACCELERATION: f64 = 200.0 * 1313.2 * (1313.2 / 117.454) / 3_027_957.0;
+        const ALPHA_STABILITY: f64 = -6.0; // Pitch acceleration per radian of alpha
+        const PITCH_DAMPING: f64 = -2.0; // Pitch acceleration per radian per second of pitch rate
+        const LIFT_SLOPE: f64 = 1.0; // Rate at which the flight path follows the pitch attitude
+        const SPEED_OVER_G: f64 = 410.0 / 32.174; // At 200 psf, in seconds
+
+        fn new() -> PitchModel {
+            let aerodynamics = |key| flight_model::value("AERODYNAMICS", key).unwrap();
+            let geometry = |key| flight_model::value("AIRPLANE_GEOMETRY", key).unwrap();
+            PitchModel {
+                flaps_moment: aerodynamics("pitch_moment_flaps"),
+                elevator_moment: aerodynamics("pitch_moment_delta_elevator"),
+                elevator_up_limit: Angle::from_degrees(geometry("elevator_up_limit")),
+                elevator_down_limit: Angle::from_degrees(geometry("elevator_down_limit")),
+                full_flaps_angle: Angle::from_degrees(
+                    flight_model::values("FLAPS.0", "flaps-position.4").unwrap()[0],
+                ),
+                // Without its L:vars, which are only registered by Controls::init
+                elevator: surfaces::elevator("", ""),
+                alpha: 0.0,
+                pitch_rate: 0.0,
+                pitch: 0.0,
+            }
+        }
+
+        fn gforce(&self) -> f64 {
+            1.0 + Self::SPEED_OVER_G * Self::LIFT_SLOPE * self.alpha
+        }
+
+        // Moves the elevator towards the demand, and the aircraft with the pitch moment of the
+        // elevator and the flaps
+        fn update(&mut self, elevator: f64, flaps_angle: Angle, dt: f64) {
+            let pressure = Pressure::from_pounds_per_square_foot(200.0);
+            let elevator = self.elevator.update(elevator, true, pressure, dt);
+            // The elevator is positive trailing edge up, the flight model deflection trailing
+            // edge down
+            let limit = if elevator >= 0.0 {
+                self.elevator_up_limit
+            } else {
+                self.elevator_down_limit
+            };
+            let moment = flaps_angle / self.full_flaps_angle * self.flaps_moment
+                - elevator * limit.radians() * self.elevator_moment;
+
+            let pitch_acceleration = Self::MOMENT_ACCELERATION * moment
+                + Self::ALPHA_STABILITY * self.alpha
+                + Self::PITCH_DAMPING * self.pitch_rate;
+            self.pitch_rate += pitch_acceleration * dt;
+            self.alpha += (self.pitch_rate - Self::LIFT_SLOPE * self.alpha) * dt;
+            self.pitch += self.pitch_rate * dt;
+        }
+    }
+
+    // The largest pitch attitude change while the flaps extend to CONF 1+F, flown by the
+    // normal law with or without the configuration compensation
+    fn flaps_extension_excursion(compensated: bool) -> Angle {
+        let (flight, protections, autopilot) = (
+            pitch_control(PitchControlMode::Flight),
+            protections(),
+            Autopilot::default(),
+        );
+        let compensation = PitchCompensation::load().unwrap();
+        let mut laws = Laws::default();
+        let mut model = PitchModel::new();
+        let mut excursion = Angle::default();
+
+        for frame in 0..(20.0 / DT) as usize {
+            // The flaps extend at the flight model rate, 35 degrees in 12 seconds
+            let flaps_angle = Angle::from_degrees((frame as f64 * DT * 35.0 / 12.0).min(10.0));
+            let pitch_compensation = if compensated {
+                compensation.elevator(flaps_angle, 0.0, 0.0)
+            } else {
+                0.0
+            };
+            let inputs = LawInputs {
+                pitch: Angle::from_degrees(2.5) + Angle::from_radians(model.pitch),
+                pitch_rate: AngularVelocity::from_degrees_per_second(model.pitch_rate.to_degrees()),
+                gforce: model.gforce(),
+                flaps: 1,
+                ..level(&flight, &protections, &autopilot)
+            };
+            let (elevator, _) = laws.fly(ControlLaw::Normal, pitch_compensation, &inputs);
+            model.update(elevator, flaps_angle, DT);
+            excursion = excursion.max(Angle::from_radians(model.pitch).abs());
+        }
+        excursion
+    }
+
+    #[test]
+    fn flaps_extension_pitch_excursion() {
+        let compensated = flaps_extension_excursion(true);
+        let uncompensated = flaps_extension_excursion(false);
+
+        // The compensation is only late by the elevator actuator, where the load factor law
+        // waits for the aircraft to pitch
+        assert!(compensated < Angle::from_degrees(0.1));
+        assert!(compensated < uncompensated / 10.0);
+    }
+
+    #[test]
+    fn normal_law_resumes_without_a_bump() {
+        let (flight, protections, autopilot) = (
+            pitch_control(PitchControlMode::Flight),
+            protections(),
+            Autopilot::default(),
+        );
+        let mut laws = Laws::default();
+
+        // Normal law holds a 25 degree bank, and pushes against a load factor above the neutral
+        let banked = LawInputs {
+            roll: Angle::from_degrees(25.0),
+            gforce: 1.2,
+            ..level(&flight, &protections, &autopilot)
+        };
+        for _ in 0..100 {
+            laws.fly(ControlLaw::Normal, 0.0, &banked);
+        }
+
+        // The hydraulics are lost for a while, and the aircraft is flown direct to a 10 degree bank
+        let direct = LawInputs {
+            yoke_x: 0.1,
+            yoke_y: 0.05,
+            roll: Angle::from_degrees(10.0),
+            ..level(&flight, &protections, &autopilot)
+        };
+        for _ in 0..100 {
+            assert_eq!(laws.fly(ControlLaw::Direct, 0.0, &direct), (0.05, 0.1));
+        }
+
+        // Normal law takes over from the surfaces as they are, holding the current bank angle
+        // rather than the one it held before
+        let resumed = LawInputs {
+            yoke_x: 0.0,
+            yoke_y: 0.0,
+            ..direct
+        };
+        let (pitch, roll) = laws.fly(ControlLaw::Normal, 0.0, &resumed);
+        assert_close(pitch, 0.05, 1e-12);
+        assert_close(roll, 0.1, 1e-12);
+        assert_close(laws.roll_controller.roll.degrees(), 10.0, 1e-12);
+        for _ in 0..50 {
+            let (next_pitch, next_roll) = laws.fly(ControlLaw::Normal, 0.0, &resumed);
+            assert!((next_roll - roll).abs() < 0.05);
+            assert!((next_pitch - pitch).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn roll_law_is_blended_in_after_liftoff_and_out_after_touchdown() {
+        let (ground, flight, derotation, protections, autopilot) = (
+            pitch_control(PitchControlMode::Ground),
+            pitch_control(PitchControlMode::Flight),
+            pitch_control(PitchControlMode::Derotation),
+            protections(),
+            Autopilot::default(),
+        );
+        let mut controller = RollController::default();
+        let mut fly = |ailerons, inputs: &LawInputs, seconds: f64| {
+            let mut output = 0.0;
+            for _ in 0..(seconds / DT).round() as usize {
+                output = controller.calculate(ailerons, inputs);
+            }
+            output
+        };
+
+        // On the ground the roll is direct
+        let taxiing = LawInputs {
+            yoke_x: 0.4,
+            ..level(&ground, &protections, &autopilot)
+        };
+        assert_eq!(fly(0.3, &taxiing, 1.0), 0.4);
+
+        // After liftoff the roll law, which holds the ailerons where they are, takes over in 5 s
+        let airborne = level(&flight, &protections, &autopilot);
+        assert_close(fly(0.3, &airborne, 1.0), 0.06, 1e-6);
+        assert_close(fly(0.3, &airborne, 1.5), 0.15, 1e-6);
+        assert_close(fly(0.3, &airborne, 2.5), 0.3, 1e-6);
+        assert_close(fly(0.3, &airborne, 1.0), 0.3, 1e-6);
+
+        // After touchdown the roll is direct again in 0.5 s
+        let landed = level(&derotation, &protections, &autopilot);
+        assert_close(fly(0.3, &landed, 0.2), 0.18, 1e-6);
+        assert_eq!(fly(0.3, &landed, 0.3), 0.0);
+        let steered = LawInputs {
+            yoke_x: 0.1,
+            ..landed
+        };
+        assert_eq!(fly(0.3, &steered, 1.0), 0.1);
+    }
+
+    #[test]
+    fn steering_needs_yellow_hydraulics() {
+        let steering = Controls::steering;
+        let all = Hydraulics::pressurised(true, true, true);
+        let yellow = Hydraulics::pressurised(false, false, true);
+        let green_blue = Hydraulics::pressurised(true, true, false);
+
+        assert_eq!(steering(0.5, true, &all), 0.5);
+        assert_eq!(steering(-0.8, true, &yellow), -0.8);
+        assert_eq!(steering(0.5, true, &green_blue), 0.0);
+        // The pedals only steer on the ground
+        assert_eq!(steering(0.5, false, &all), 0.0);
+    }
 }
@@ -1,137 +1,567 @@
-use crate::{fbw::FBW, Result};
+use crate::{
+    atmosphere::{self, AirData},
+    clamp,
+    fbw::FBW,
+    history::{FrameHistory, Timestamped},
+    signal::LowPass,
+    units::{
+        Angle, AngularVelocity, Density, Force, Length, Mass, Pressure, Temperature, Velocity,
+        VelocityVector,
+    },
+    Result,
+};
+use msfs::{
+    sim_connect::{data_definition, SimConnect},
+    sys::{SIMCONNECT_OBJECT_ID_USER, SIMCONNECT_PERIOD_SIM_FRAME, SIMCONNECT_RECV_SIMOBJECT_DATA},
+};
 
 #[derive(Default, Clone, serde::Deserialize)]
 pub(crate) struct DataFrame {
-    aileron: f64,         // Aileron input deflection (-1.0.0 full left, +1.0.0 full right)y
-    altitude: f64,        // The altitude in feet
-    aoa: f64,             // The angle of attack in degrees
-    autopilot: bool,      // True if the autopilot is on
-    cg_lateral: f64,      // The lateral CG as a percent of the reference chord
-    cg_longitudinal: f64, // The longitudinal CG as a percent of the reference chord
-    density: f64,         // The density of the air in slugs per cubic feet
-    elevator: f64,        // Elevator input deflection (-1.0.0 full down, +1.0.0 full up)
-    elevator_trim: f64,   // Elevator trim in degrees (+13.5 full up, -4.0.0 full down)
-    engine_thrust_1: f64, // Engine #1 thrust in pounds
-    engine_thrust_2: f64, // Engine #2 thrust in pounds
-    flaps: u8, // The current position of the flaps handle (0.0 = Clean CONF, 4 = CONF FULL)
-    gforce: f64, // The current gforce (load factor)
-    ias: f64,  // The indicated airspeed in knots
-    mach: f64, // The current speed in mach
-    mmo: f64,  // The Mmo speed in mach
+    aileron: f64,              // Aileron input deflection (-1.0 full left, +1.0 full right)
+    altitude: Length,          // The altitude
+    aoa: Angle,                // The filtered angle of attack
+    aoa_valid: bool,           // True if the angle of attack is available
+    autopilot: bool,           // True if the autopilot is on
+    cg_lateral: f64,           // The lateral CG as a percent of the reference chord
+    cg_longitudinal: f64,      // The longitudinal CG as a percent of the reference chord
+    density: Density,          // The density of the air
+    elevator: f64,             // Elevator input deflection (-1.0 full down, +1.0 full up)
+    elevator_trim: Angle,      // Elevator trim (+13.5 degrees full up, -4.0 degrees full down)
+    elevator_trim_valid: bool, // True if the elevator trim is available
+    engine_thrust_1: Force,    // Engine #1 thrust
+    engine_thrust_2: Force,    // Engine #2 thrust
+    flaps: u8, // The current position of the flaps handle (0 = Clean CONF, 4 = CONF FULL)
+    flaps_angle: Angle, // The trailing edge flaps deflection
+    flaps_extended: bool, // True if the trailing edge flaps are extended
+    flight_director_bank: Angle, // Flight director bank command
+    flight_director_pitch: Angle, // Flight director pitch command
+    gear_extension: f64, // Landing gear extension (0.0 = up and locked, 1.0 = down and locked)
+    gear_handle_down: bool, // True if the landing gear handle is down
+    heading: Angle, // True heading
+    gforce: f64, // The current filtered gforce (load factor)
+    gforce_valid: bool, // True if the gforce is available
+    ias: Velocity, // The indicated airspeed
     on_ground: bool, // True if the plane is on the ground
-    pitch: f64, // Pitch attitude in degrees (+ is up, - is down)
-    radio_height: f64, // Radio altimeter in feet
-    roll: f64, // Roll attitude in degrees (+ is right, - is left)
-    rudder: f64, // Rudder input deflection (-1.0.0 full down, +1.0.0 full up)
+    nose_gear_on_ground: bool, // True if the nose gear is on the ground
+    pitch: Angle, // Pitch attitude
+    pitch_rate: AngularVelocity, // Pitch attitude rate
+    pitch_rate_valid: bool, // True if the pitch rate is available
+    radio_height: Length, // Radio altimeter height
+    received: bool, // True once SimConnect has sent the first batch of SimVars
+    roll: Angle, // Roll attitude
+    rudder: f64, // Rudder input deflection (-1.0 full left, +1.0 full right)
     sim_controllable: bool, // True if the sim might be controllable
-    speed_lateral: f64, // Lateral speed (relative to the earth in a north/south direction) in feet/second
-    speed_longitudinal: f64, // Longitudinal speed (relative to the earth in an east/west direction) in feet/second
-    speed_vertical: f64,     // Vertical speed (relative to the earth) in feet/second
-    tas: f64,                // The true airspeed in knots
-    time: f64,               // The simulation time
-    vmo: f64,                // The Vmo speed in knots
-    weight: f64,             // Total weight of the airplane in pounds
-    wind_lateral: f64, // Lateral wind (relative to the earth in a north/south direction) in feet/second
-    wind_longitudinal: f64, // Longitudinal wind (relative to the earth in a east/west direction) in feet/second
-    wind_vertical: f64,     // Vertical wind (relative ot the earth) in feet/second
+    speedbrake_handle: f64, // Speedbrake handle position (0.0 retracted, 1.0 full)
+    speed_lateral: Velocity, // Lateral speed (relative to the earth in a north/south direction)
+    speed_longitudinal: Velocity, // Longitudinal speed (relative to the earth in an east/west direction)
+    speed_vertical: Velocity,     // Vertical speed (relative to the earth)
+    tas: Velocity,                // The true airspeed
+    time: f64,                    // The simulation time in seconds
+    static_pressure: Pressure,    // The ambient static pressure
+    temperature: Temperature,     // The static air temperature
+    weight: Mass,                 // Total weight of the airplane
+    wind_lateral: Velocity,       // Lateral wind (relative to the earth in a north/south direction)
+    wind_longitudinal: Velocity, // Longitudinal wind (relative to the earth in a east/west direction)
+    wind_vertical: Velocity,     // Vertical wind (relative to the earth)
+
+    // Derived quantities, computed from the fields above
+    total_pressure: Pressure, // The total pressure sensed by the pitot probes
+    air_data: AirData,        // Air data computed from the pressures and temperature
+    air_velocity: VelocityVector, // Velocity relative to the air mass, in earth axes
+    flight_path_angle: Angle, // Flight path angle relative to the ground (+ is climbing)
+    track: Angle,             // True ground track
+    sideslip: Angle,          // Sideslip angle (+ is the relative wind coming from the right)
+    expected_load_factor: f64, // The load factor to hold the flight path in a steady turn
+    lift_coefficient: f64,    // Estimated lift coefficient
+}
+
+impl Timestamped for DataFrame {
+    fn time(&self) -> f64 {
+        self.time
+    }
+}
+
+const SIM_VARS: u32 = 1;
+const SIM_VARS_REQUEST: u32 = 0;
+
+// The SimVars read by the FBW, requested from SimConnect once per sim frame in a single batch.
+// Booleans are received as numbers, and a NaN marks a value the sim couldn't provide.
+#[data_definition]
+#[derive(Default, Clone)]
+struct SimVars {
+    #[name = "AILERON POSITION"]
+    #[unit = "Position"]
+    aileron: f64,
+    #[name = "PLANE ALTITUDE"]
+    #[unit = "Feet"]
+    altitude: f64,
+    #[name = "INCIDENCE ALPHA"]
+    #[unit = "Degrees"]
+    aoa: f64,
+    #[name = "AUTOPILOT MASTER"]
+    #[unit = "Bool"]
+    autopilot: f64,
+    #[name = "CG PERCENT LATERAL"]
+    #[unit = "Percent"]
+    cg_lateral: f64,
+    #[name = "CG PERCENT"]
+    #[unit = "Percent"]
+    cg_longitudinal: f64,
+    #[name = "AMBIENT DENSITY"]
+    #[unit = "Slugs per cubic feet"]
+    density: f64,
+    #[name = "ELEVATOR POSITION"]
+    #[unit = "Position"]
+    elevator: f64,
+    #[name = "ELEVATOR TRIM POSITION"]
+    #[unit = "Degrees"]
+    elevator_trim: f64,
+    #[name = "ENG COMBUSTION:1"]
+    #[unit = "Bool"]
+    engine_combustion_1: f64,
+    #[name = "ENG COMBUSTION:2"]
+    #[unit = "Bool"]
+    engine_combustion_2: f64,
+    #[name = "TURB ENG JET THRUST:1"]
+    #[unit = "Pounds"]
+    engine_thrust_1: f64,
+    #[name = "TURB ENG JET THRUST:2"]
+    #[unit = "Pounds"]
+    engine_thrust_2: f64,
+    #[name = "FLAPS HANDLE INDEX"]
+    #[unit = "Number"]
+    flaps: f64,
+    #[name = "TRAILING EDGE FLAPS LEFT ANGLE"]
+    #[unit = "Degrees"]
+    flaps_angle: f64,
+    #[name = "GEAR TOTAL PCT EXTENDED"]
+    #[unit = "Percent over 100"]
+    gear_extension: f64,
+    #[name = "GEAR HANDLE POSITION"]
+    #[unit = "Bool"]
+    gear_handle: f64,
+    #[name = "AUTOPILOT FLIGHT DIRECTOR BANK"]
+    #[unit = "Degrees"]
+    flight_director_bank: f64,
+    #[name = "AUTOPILOT FLIGHT DIRECTOR PITCH"]
+    #[unit = "Degrees"]
+    flight_director_pitch: f64,
+    #[name = "PLANE HEADING DEGREES TRUE"]
+    #[unit = "Degrees"]
+    heading: f64,
+    #[name = "G FORCE"]
+    #[unit = "GForce"]
+    gforce: f64,
+    #[name = "AIRSPEED INDICATED"]
+    #[unit = "Knots"]
+    ias: f64,
+    #[name = "SIM ON GROUND"]
+    #[unit = "Bool"]
+    on_ground: f64,
+    #[name = "CONTACT POINT IS ON GROUND:0"]
+    #[unit = "Bool"]
+    nose_gear_on_ground: f64,
+    #[name = "PLANE PITCH DEGREES"]
+    #[unit = "Degrees"]
+    pitch: f64,
+    #[name = "ROTATION VELOCITY BODY X"]
+    #[unit = "Degrees per second"]
+    body_pitch_rate: f64,
+    #[name = "ROTATION VELOCITY BODY Y"]
+    #[unit = "Degrees per second"]
+    body_yaw_rate: f64,
+    #[name = "RADIO HEIGHT"]
+    #[unit = "Feet"]
+    radio_height: f64,
+    #[name = "PLANE BANK DEGREES"]
+    #[unit = "Degrees"]
+    roll: f64,
+    #[name = "RUDDER POSITION"]
+    #[unit = "Position"]
+    rudder: f64,
+    #[name = "IS LATITUDE LONGITUDE FREEZE ON"]
+    #[unit = "Bool"]
+    freeze_position: f64,
+    #[name = "IS ALTITUDE FREEZE ON"]
+    #[unit = "Bool"]
+    freeze_altitude: f64,
+    #[name = "IS ATTITUDE FREEZE ON"]
+    #[unit = "Bool"]
+    freeze_attitude: f64,
+    #[name = "SIM DISABLED"]
+    #[unit = "Bool"]
+    sim_disabled: f64,
+    #[name = "IS SLEW ACTIVE"]
+    #[unit = "Bool"]
+    slew_active: f64,
+    #[name = "SPOILERS HANDLE POSITION"]
+    #[unit = "Percent over 100"]
+    speedbrake_handle: f64,
+    #[name = "VELOCITY WORLD Z"]
+    #[unit = "Feet per second"]
+    speed_lateral: f64,
+    #[name = "VELOCITY WORLD X"]
+    #[unit = "Feet per second"]
+    speed_longitudinal: f64,
+    #[name = "VELOCITY WORLD Y"]
+    #[unit = "Feet per second"]
+    speed_vertical: f64,
+    #[name = "AIRSPEED TRUE"]
+    #[unit = "Knots"]
+    tas: f64,
+    #[name = "AMBIENT PRESSURE"]
+    #[unit = "Millibars"]
+    static_pressure: f64,
+    #[name = "AMBIENT TEMPERATURE"]
+    #[unit = "Celsius"]
+    temperature: f64,
+    #[name = "TOTAL WEIGHT"]
+    #[unit = "Pounds"]
+    weight: f64,
+    #[name = "AMBIENT WIND Z"]
+    #[unit = "Feet per second"]
+    wind_lateral: f64,
+    #[name = "AMBIENT WIND X"]
+    #[unit = "Feet per second"]
+    wind_longitudinal: f64,
+    #[name = "AMBIENT WIND Y"]
+    #[unit = "Feet per second"]
+    wind_vertical: f64,
 }
 
 #[derive(Clone)]
 pub(crate) struct Data {
-    frames: Vec<DataFrame>,
+    sim_vars: Option<SimVars>, // The latest SimVars received, None until the first batch arrives
+    engine_running: [bool; 2],
+    frames: FrameHistory<DataFrame>,
+    aoa_filter: LowPass,
+    gforce_filter: LowPass,
+    pitch_rate_filter: LowPass,
 }
 
 impl Default for Data {
     fn default() -> Data {
         Data {
-            frames: vec![DataFrame::default(); 50],
+            sim_vars: None,
+            engine_running: [false; 2],
+            frames: FrameHistory::new(Self::HISTORY_FRAMES),
+            aoa_filter: LowPass::from_cutoff(Self::AOA_CUTOFF),
+            gforce_filter: LowPass::from_cutoff(Self::GFORCE_CUTOFF),
+            pitch_rate_filter: LowPass::from_cutoff(Self::PITCH_RATE_CUTOFF),
         }
     }
 }
 
 impl Data {
+    // Number of frames kept in the history
+    const HISTORY_FRAMES: usize = 50;
+
+    // Default cut-off frequencies (in hertz) of the sensor filters. They can be changed through the
+    // A32NX_FBW_AOA_CUTOFF and A32NX_FBW_GFORCE_CUTOFF L:vars.
+    const AOA_CUTOFF: f64 = 2.0;
+    const GFORCE_CUTOFF: f64 = 3.0;
+    // Only used when the pitch rate has to be estimated from the pitch attitude
+    const PITCH_RATE_CUTOFF: f64 = 5.0;
+
+    // The A320 reference wing area in square feet (122.6 square meters)
+    const WING_AREA: f64 = 1319.7;
+    // Below this dynamic pressure (in pounds per square foot), the lift coefficient is meaningless
+    const MIN_DYNAMIC_PRESSURE: f64 = 1.0;
+    // cos(84 degrees), the bank angle beyond which the expected load factor stops increasing
+    const MIN_ROLL_COSINE: f64 = 0.1;
+
+    // The data is updated first in each frame, so there is always a current frame
     fn current_frame(&self) -> &DataFrame {
-        self.frames.last().unwrap()
+        self.frames.latest().unwrap()
+    }
+
+    pub(crate) fn init(&mut self, sim: &SimConnect) -> Result<()> {
+        sim.add_data_definition::<SimVars>(SIM_VARS)?;
+        sim.request_data_on_sim_object::<SimVars>(
+            SIM_VARS_REQUEST,
+            SIM_VARS,
+            SIMCONNECT_OBJECT_ID_USER,
+            SIMCONNECT_PERIOD_SIM_FRAME,
+        )?;
+
+        Ok(())
     }
-    fn previous_frame(&self) -> &DataFrame {
-        &self.frames[self.frames.len() - 2]
+
+    // Stores a batch of SimVars received from SimConnect, for the next update
+    pub(crate) fn receive(&mut self, data: &SIMCONNECT_RECV_SIMOBJECT_DATA, sim: &SimConnect) {
+        if data.dwRequestID != SIM_VARS_REQUEST {
+            return;
+        }
+        if let Some(sim_vars) = data.into::<SimVars>(sim) {
+            self.sim_vars = Some(sim_vars.clone());
+        }
     }
 
     pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
-        self.frames.rotate_right(1);
-        let mut frame = self.frames.last_mut().unwrap();
-
-        let fetch = |name, units, index, fallback| {
-            use msfs::msfs::legacy::*;
-            let r = aircraft_varget(get_aircraft_var_enum(name), get_units_enum(units), index);
-            if r.is_nan() {
-                fallback
+        // The filter cut-offs can be tuned in the sim
+        let cutoff = |name, default| {
+            use msfs::msfs::legacy::NamedVariable;
+            let cutoff = NamedVariable::from(name).get_value::<f64>();
+            if cutoff > 0.0 {
+                cutoff
             } else {
-                r
+                default
             }
         };
+        self.aoa_filter
+            .set_cutoff(cutoff("A32NX_FBW_AOA_CUTOFF", Self::AOA_CUTOFF));
+        self.gforce_filter
+            .set_cutoff(cutoff("A32NX_FBW_GFORCE_CUTOFF", Self::GFORCE_CUTOFF));
 
-        frame.aileron = fetch("AILERON POSITION", "Position", 0, 0.0);
-        frame.altitude = fetch("PLANE ALTITUDE", "Feet", 0, 0.0);
-        frame.aoa = fetch("INCIDENCE ALPHA", "Degrees", 0, 0.0);
-        frame.autopilot = fetch("AUTOPILOT MASTER", "Bool", 0, 0.0) == 0.0;
-        frame.cg_lateral = fetch("CG PERCENT LATERAL", "Percent", 0, 0.0);
-        frame.cg_longitudinal = fetch("CG PERCENT", "Percent", 0, 0.0);
-        frame.density = fetch("AMBIENT DENSITY", "Slugs per cubic feet", 0, 0.0);
-        frame.elevator = fetch("ELEVATOR POSITION", "Position", 0, 0.0);
-        frame.elevator_trim = fetch("ELEVATOR TRIM POSITION", "Degrees", 0, 0.0);
-        frame.engine_thrust_1 = fetch("TURB ENG JET THRUST", "Pounds", 1, 0.0);
-        frame.engine_thrust_2 = fetch("TURB ENG JET THRUST", "Pounds", 2, 0.0);
-        frame.flaps = fetch("FLAPS HANDLE INDEX", "Number", 0, 0.0) as u8;
-        frame.gforce = fetch("G FORCE", "GForce", 0, 0.0);
-        frame.ias = fetch("AIRSPEED INDICATED", "Knots", 0, 0.0);
-        frame.mach = fetch("AIRSPEED MACH", "Mach", 0, 0.0);
-        frame.mmo = fetch("BARBER POLE MACH", "Mach", 0, f64::MAX); // TODO: Get this data from the FCOM instead of the SimVar
-        frame.on_ground = fetch("SIM ON GROUND", "Bool", 0, 0.0) == 0.0;
-        frame.pitch = -fetch("PLANE PITCH DEGREES", "Degrees", 0, 0.0);
-        frame.radio_height = fetch("RADIO HEIGHT", "Feet", 0, 0.0);
-        frame.roll = -fetch("PLANE BANK DEGREES", "Degrees", 0, 0.0);
-        frame.rudder = fetch("RUDDER POSITION", "Position", 0, 0.0);
-        frame.sim_controllable = fetch("IS LATITUDE LONGITUDE FREEZE ON", "Bool", 0, 0.0) == 0.0
-            && fetch("IS ALTITUDE FREEZE ON", "Bool", 0, 0.0) == 0.0
-            && fetch("IS ATTITUDE FREEZE ON", "Bool", 0, 0.0) == 0.0
-            && fetch("SIM DISABLED", "Bool", 0, 0.0) == 0.0
-            && fetch("IS SLEW ACTIVE", "Bool", 0, 0.0) == 0.0;
-        frame.speed_lateral = fetch("VELOCITY WORLD Z", "Feet per second", 0, 0.0);
-        frame.speed_longitudinal = fetch("VELOCITY WORLD X", "Feet per second", 0, 0.0);
-        frame.speed_vertical = fetch("VELOCITY WORLD Y", "Feet per second", 0, 0.0);
-        frame.tas = fetch("AIRSPEED true", "Knots", 0, 0.0);
-        frame.time = ctx.sim_time.current();
-        frame.vmo = fetch("AIRSPEED BARBER POLE", "Knots", 0, f64::MAX); // TODO: Get this data from the FCOM instead of the SimVar
-        frame.weight = fetch("TOTAL WEIGHT", "Pounds", 0, 0.0);
-        frame.wind_lateral = fetch("AMBIENT WIND Z", "Feet per second", 0, 0.0);
-        frame.wind_longitudinal = fetch("AMBIENT WIND X", "Feet per second", 0, 0.0);
-        frame.wind_vertical = fetch("AMBIENT WIND Y", "Feet per second", 0, 0.0);
+        self.process(ctx.sim_time.current(), ctx.sim_time.delta());
 
         Ok(())
     }
 
-    pub(crate) fn alpha(&self) -> f64 {
+    // Turns the latest SimVars into the frame of the given time
+    fn process(&mut self, time: f64, dt: f64) {
+        let (previous_aoa, previous_gforce, previous_pitch, previous_pitch_rate) =
+            match self.frames.latest() {
+                Some(previous) => (
+                    previous.aoa,
+                    previous.gforce,
+                    previous.pitch,
+                    previous.pitch_rate,
+                ),
+                None => (
+                    Angle::default(),
+                    0.0,
+                    Angle::default(),
+                    AngularVelocity::default(),
+                ),
+            };
+        let mut frame = DataFrame::default();
+
+        // Until SimConnect has sent the first batch, every value is unavailable
+        let vars = self.sim_vars.clone().unwrap_or_default();
+        let received = self.sim_vars.is_some();
+        let read = |value: f64| {
+            if received && !value.is_nan() {
+                Some(value)
+            } else {
+                None
+            }
+        };
+        let fetch = |value, fallback| read(value).unwrap_or(fallback);
+        let is_set = |value| fetch(value, 0.0) != 0.0;
+
+        self.engine_running = [
+            is_set(vars.engine_combustion_1),
+            is_set(vars.engine_combustion_2),
+        ];
+
+        frame.aileron = fetch(vars.aileron, 0.0);
+        frame.altitude = Length::from_feet(fetch(vars.altitude, 0.0));
+        frame.autopilot = is_set(vars.autopilot);
+        frame.cg_lateral = fetch(vars.cg_lateral, 0.0);
+        frame.cg_longitudinal = fetch(vars.cg_longitudinal, 0.0);
+        frame.density = Density::from_slugs_per_cubic_foot(fetch(vars.density, 0.0));
+        frame.elevator = fetch(vars.elevator, 0.0);
+        frame.elevator_trim = Angle::from_degrees(fetch(vars.elevator_trim, 0.0));
+        frame.elevator_trim_valid = read(vars.elevator_trim).is_some();
+        frame.engine_thrust_1 = Force::from_pounds_force(fetch(vars.engine_thrust_1, 0.0));
+        frame.engine_thrust_2 = Force::from_pounds_force(fetch(vars.engine_thrust_2, 0.0));
+        frame.flaps = fetch(vars.flaps, 0.0) as u8;
+        frame.flight_director_bank =
+            Angle::from_sim_attitude(fetch(vars.flight_director_bank, 0.0));
+        frame.flight_director_pitch =
+            Angle::from_sim_attitude(fetch(vars.flight_director_pitch, 0.0));
+        frame.flaps_angle = Angle::from_degrees(fetch(vars.flaps_angle, 0.0));
+        frame.flaps_extended = frame.flaps_angle > Angle::default();
+        frame.gear_extension = fetch(vars.gear_extension, 0.0);
+        frame.gear_handle_down = is_set(vars.gear_handle);
+        frame.heading = Angle::from_degrees(fetch(vars.heading, 0.0));
+        frame.ias = Velocity::from_knots(fetch(vars.ias, 0.0));
+        frame.on_ground = fetch(vars.on_ground, 0.0) != 0.0;
+        frame.nose_gear_on_ground = is_set(vars.nose_gear_on_ground);
+        frame.pitch = Angle::from_sim_attitude(fetch(vars.pitch, 0.0));
+        frame.radio_height = Length::from_feet(fetch(vars.radio_height, 0.0));
+        frame.received = received;
+        frame.roll = Angle::from_sim_attitude(fetch(vars.roll, 0.0));
+        frame.rudder = fetch(vars.rudder, 0.0);
+        frame.sim_controllable = !is_set(vars.freeze_position)
+            && !is_set(vars.freeze_altitude)
+            && !is_set(vars.freeze_attitude)
+            && !is_set(vars.sim_disabled)
+            && !is_set(vars.slew_active);
+        frame.speedbrake_handle = fetch(vars.speedbrake_handle, 0.0);
+        frame.speed_lateral = Velocity::from_feet_per_second(fetch(vars.speed_lateral, 0.0));
+        frame.speed_longitudinal =
+            Velocity::from_feet_per_second(fetch(vars.speed_longitudinal, 0.0));
+        frame.speed_vertical = Velocity::from_feet_per_second(fetch(vars.speed_vertical, 0.0));
+        frame.tas = Velocity::from_knots(fetch(vars.tas, 0.0));
+        frame.time = time;
+        frame.static_pressure = Pressure::from_pascals(100.0 * fetch(vars.static_pressure, 0.0));
+        frame.temperature = Temperature::from_celsius(fetch(vars.temperature, 0.0));
+        frame.weight = Mass::from_pounds(fetch(vars.weight, 0.0));
+        frame.wind_lateral = Velocity::from_feet_per_second(fetch(vars.wind_lateral, 0.0));
+        frame.wind_longitudinal =
+            Velocity::from_feet_per_second(fetch(vars.wind_longitudinal, 0.0));
+        frame.wind_vertical = Velocity::from_feet_per_second(fetch(vars.wind_vertical, 0.0));
+
+        // The conditioned signals are filtered, and flagged invalid when unavailable (the last
+        // valid value is kept).
+        let (aoa, aoa_valid) = Self::condition(
+            &mut self.aoa_filter,
+            read(vars.aoa),
+            previous_aoa.degrees(),
+            dt,
+        );
+        frame.aoa = Angle::from_degrees(aoa);
+        frame.aoa_valid = aoa_valid;
+
+        let (gforce, gforce_valid) = Self::condition(
+            &mut self.gforce_filter,
+            read(vars.gforce),
+            previous_gforce,
+            dt,
+        );
+        frame.gforce = gforce;
+        frame.gforce_valid = gforce_valid;
+
+        let body_rates = read(vars.body_pitch_rate).zip(read(vars.body_yaw_rate));
+        let pitch_change = read(vars.pitch).map(|_| frame.pitch - previous_pitch);
+        let (pitch_rate, pitch_rate_valid) = Self::condition_pitch_rate(
+            &mut self.pitch_rate_filter,
+            body_rates,
+            frame.roll,
+            pitch_change,
+            previous_pitch_rate,
+            dt,
+        );
+        frame.pitch_rate = pitch_rate;
+        frame.pitch_rate_valid = pitch_rate_valid;
+
+        Self::derive(&mut frame);
+        self.frames.push(frame);
+    }
+
+    // Filters a sensor value while it is available. When it isn't, the last value is kept and
+    // flagged invalid.
+    fn condition(filter: &mut LowPass, value: Option<f64>, last: f64, dt: f64) -> (f64, bool) {
+        match value {
+            Some(value) => (filter.update(value, dt), true),
+            None => (last, false),
+        }
+    }
+
+    // The pitch attitude rate, and whether it is valid.
+    // It is taken from the sim body rotation rates (pitch, yaw) in degrees per second, which is
+    // correct when banked: pitch_rate = q * cos(roll) - r * sin(roll). The sim body Y rate is
+    // positive nose right.
+    // Without them, it is estimated from the change of the pitch attitude since the last frame.
+    fn condition_pitch_rate(
+        filter: &mut LowPass,
+        body_rates: Option<(f64, f64)>,
+        roll: Angle,
+        pitch_change: Option<Angle>,
+        last: AngularVelocity,
+        dt: f64,
+    ) -> (AngularVelocity, bool) {
+        match (body_rates, pitch_change) {
+            (Some((q, r)), _) => {
+                let q = AngularVelocity::from_sim_body_rate(q);
+                let r = AngularVelocity::from_degrees_per_second(r);
+                (q * roll.cos() - r * roll.sin(), true)
+            }
+            (None, Some(change)) if dt > 0.0 => {
+                let rate = AngularVelocity::from_change(change, dt);
+                let rate = filter.update(rate.degrees_per_second(), dt);
+                (AngularVelocity::from_degrees_per_second(rate), true)
+            }
+            _ => (last, false),
+        }
+    }
+
+    // Computes the derived flight state of a frame
+    fn derive(frame: &mut DataFrame) {
+        // The sim doesn't provide the pitot pressure, so it is computed from the true airspeed.
+        // The air data is then computed from the pressures like the air data computers do.
+        frame.total_pressure =
+            atmosphere::total_pressure(frame.tas, frame.static_pressure, frame.temperature);
+        frame.air_data = AirData::compute(
+            frame.static_pressure,
+            frame.total_pressure,
+            frame.temperature,
+        );
+
+        // The sim world X axis points east, and its Z axis north
+        let ground_velocity = VelocityVector {
+            north: frame.speed_lateral,
+            east: frame.speed_longitudinal,
+            up: frame.speed_vertical,
+        };
+        let wind = VelocityVector {
+            north: frame.wind_lateral,
+            east: frame.wind_longitudinal,
+            up: frame.wind_vertical,
+        };
+        frame.air_velocity = ground_velocity - wind;
+        frame.track = ground_velocity.azimuth();
+        frame.flight_path_angle = ground_velocity.elevation();
+
+        // The sideslip is the angle of the relative wind out of the aircraft plane of symmetry,
+        // from the component of the air velocity along the lateral body axis (the right wing).
+        let (heading, pitch, roll) = (frame.heading, frame.pitch, frame.roll);
+        let air = frame.air_velocity;
+        let lateral = air.north.knots()
+            * (roll.sin() * pitch.sin() * heading.cos() - roll.cos() * heading.sin())
+            + air.east.knots()
+                * (roll.sin() * pitch.sin() * heading.sin() + roll.cos() * heading.cos())
+            - air.up.knots() * roll.sin() * pitch.cos();
+        let airspeed = air.magnitude().knots();
+        frame.sideslip = if airspeed > 0.0 {
+            Angle::from_radians(clamp(lateral / airspeed, -1.0, 1.0).asin())
+        } else {
+            Angle::default()
+        };
+
+        // Holding the flight path in a coordinated turn requires cos(pitch) / cos(roll) g.
+        // The roll is limited so that the expectation stays finite close to 90 degrees of bank.
+        frame.expected_load_factor = pitch.cos() / roll.cos().max(Self::MIN_ROLL_COSINE);
+
+        // CL = n * W / (q * S), where the weight in pounds is numerically the weight force in lbf
+        let dynamic_pressure = frame
+            .density
+            .dynamic_pressure(frame.tas)
+            .pounds_per_square_foot();
+        frame.lift_coefficient = if dynamic_pressure > Self::MIN_DYNAMIC_PRESSURE {
+            frame.gforce * frame.weight.pounds() / (dynamic_pressure * Self::WING_AREA)
+        } else {
+            0.0
+        };
+    }
+
+    pub(crate) fn alpha(&self) -> Angle {
         self.current_frame().aoa
     }
 
-    fn alpha_floor(&self) -> f64 {
+    pub(crate) fn alpha_valid(&self) -> bool {
+        self.current_frame().aoa_valid
+    }
+
+    fn alpha_floor(&self) -> Angle {
         // These values are hardcoded in the FCOM in 1.27.20 under "High Angle of Attack Protection"
         // Note: 2. a.floor is activated through A/THR system when:
         // - a > a floor (9.5 degrees in configuration 0; 15 degrees in configuration 1, 2; 14 degrees in
         //   configuration 3; 13 degrees in configuration FULL), or,...
         // TODO: These values don't seem to mesh well with how the A320 is actually modeled, even though
         //       they come from the manual directly.
-        match self.current_frame().flaps {
+        Angle::from_degrees(match self.current_frame().flaps {
             0 => 9.5,
             1 | 2 => 15.0,
             3 => 14.0,
             4 => 13.0,
             _ => 9.5,
-        }
+        })
     }
 
-    pub(crate) fn alpha_prot(&self) -> f64 {
+    pub(crate) fn alpha_prot(&self) -> Angle {
         // This ratio was estimated using the graph in the FCOM in 1.27.20 under "High Angle of Attack Protection"
         // The graph plots CL (lift coefficient) to alpha.
         // The ratio was guesstimated using a ruler and hoping the graph was accurate.
@@ -139,7 +569,7 @@ impl Data {
         RATIO_WITH_ALPHA_FLOOR * self.alpha_floor()
     }
 
-    pub(crate) fn alpha_max(&self) -> f64 {
+    pub(crate) fn alpha_max(&self) -> Angle {
         // This ratio was estimated using the graph in the FCOM in 1.27.20 under "High Angle of Attack Protection"
         // The graph plots CL (lift coefficient) to alpha.
         // The ratio was guesstimated using a ruler and hoping the graph was accurate.
@@ -147,43 +577,533 @@ impl Data {
         RATIO_WITH_ALPHA_FLOOR * self.alpha_floor()
     }
 
+    pub(crate) fn altitude(&self) -> Length {
+        self.current_frame().altitude
+    }
+
     pub(crate) fn autopilot(&self) -> bool {
         self.current_frame().autopilot
     }
 
+    // The longitudinal CG as a percent of the reference chord
+    pub(crate) fn cg_longitudinal(&self) -> f64 {
+        self.current_frame().cg_longitudinal
+    }
+
+    pub(crate) fn dynamic_pressure(&self) -> Pressure {
+        let frame = self.current_frame();
+        frame.density.dynamic_pressure(frame.tas)
+    }
+
+    // Elevator trim (+13.5 degrees full up, -4.0 degrees full down)
+    pub(crate) fn elevator_trim(&self) -> Angle {
+        self.current_frame().elevator_trim
+    }
+
+    pub(crate) fn elevator_trim_valid(&self) -> bool {
+        self.current_frame().elevator_trim_valid
+    }
+
+    // True if the given engine (1 or 2) is running
+    pub(crate) fn engine_running(&self, engine: usize) -> bool {
+        self.engine_running[engine - 1]
+    }
+
     pub(crate) fn flaps(&self) -> u8 {
         self.current_frame().flaps
     }
 
+    pub(crate) fn flight_director_bank(&self) -> Angle {
+        self.current_frame().flight_director_bank
+    }
+
+    pub(crate) fn flight_director_pitch(&self) -> Angle {
+        self.current_frame().flight_director_pitch
+    }
+
+    pub(crate) fn flaps_angle(&self) -> Angle {
+        self.current_frame().flaps_angle
+    }
+
+    pub(crate) fn flaps_extended(&self) -> bool {
+        self.current_frame().flaps_extended
+    }
+
+    pub(crate) fn gear_extension(&self) -> f64 {
+        self.current_frame().gear_extension
+    }
+
+    pub(crate) fn gear_handle_down(&self) -> bool {
+        self.current_frame().gear_handle_down
+    }
+
+    // True while the landing gear is travelling between up and down
+    pub(crate) fn gear_in_transit(&self) -> bool {
+        let extension = self.current_frame().gear_extension;
+        extension > 0.0 && extension < 1.0
+    }
+
     pub(crate) fn gforce(&self) -> f64 {
         self.current_frame().gforce
     }
 
-    pub(crate) fn ias(&self) -> f64 {
+    pub(crate) fn gforce_valid(&self) -> bool {
+        self.current_frame().gforce_valid
+    }
+
+    pub(crate) fn ias(&self) -> Velocity {
         self.current_frame().ias
     }
 
     pub(crate) fn mach(&self) -> f64 {
-        self.current_frame().mach
+        self.current_frame().air_data.mach
+    }
+
+    pub(crate) fn nose_gear_on_ground(&self) -> bool {
+        self.current_frame().nose_gear_on_ground
     }
 
-    pub(crate) fn mmo(&self) -> f64 {
-        self.current_frame().mmo
+    pub(crate) fn on_ground(&self) -> bool {
+        self.current_frame().on_ground
     }
 
-    pub(crate) fn pitch(&self) -> f64 {
+    pub(crate) fn pitch(&self) -> Angle {
         self.current_frame().pitch
     }
 
-    pub(crate) fn pitch_rate(&self, ctx: &FBW) -> f64 {
-        (self.current_frame().pitch - self.previous_frame().pitch) / ctx.sim_time.delta()
+    pub(crate) fn pitch_rate(&self) -> AngularVelocity {
+        self.current_frame().pitch_rate
+    }
+
+    pub(crate) fn pitch_rate_valid(&self) -> bool {
+        self.current_frame().pitch_rate_valid
     }
 
-    pub(crate) fn roll(&self) -> f64 {
+    pub(crate) fn radio_height(&self) -> Length {
+        self.current_frame().radio_height
+    }
+
+    // Until the first batch of SimVars has been received, every value reads as its default
+    pub(crate) fn received(&self) -> bool {
+        self.current_frame().received
+    }
+
+    pub(crate) fn roll(&self) -> Angle {
         self.current_frame().roll
     }
 
-    pub(crate) fn vmo(&self) -> f64 {
-        self.current_frame().vmo
+    // Speedbrake handle position (0.0 retracted, 1.0 full)
+    pub(crate) fn speedbrake_handle(&self) -> f64 {
+        self.current_frame().speedbrake_handle
+    }
+
+    pub(crate) fn static_pressure(&self) -> Pressure {
+        self.current_frame().static_pressure
+    }
+
+    // The total thrust of both engines
+    pub(crate) fn thrust(&self) -> Force {
+        let frame = self.current_frame();
+        frame.engine_thrust_1 + frame.engine_thrust_2
+    }
+
+    // Vertical speed relative to the earth (+ is climbing)
+    pub(crate) fn vertical_speed(&self) -> Velocity {
+        self.current_frame().speed_vertical
+    }
+
+    pub(crate) fn weight(&self) -> Mass {
+        self.current_frame().weight
+    }
+}
+
+// Derived flight state
+// Not every derived quantity is used by the laws yet
+#[allow(dead_code)]
+impl Data {
+    // Velocity relative to the air mass, in the local earth axes
+    pub(crate) fn air_velocity(&self) -> VelocityVector {
+        self.current_frame().air_velocity
+    }
+
+    // Flight path angle relative to the ground (+ is climbing, - is descending)
+    pub(crate) fn flight_path_angle(&self) -> Angle {
+        self.current_frame().flight_path_angle
+    }
+
+    // True ground track, clockwise from north
+    pub(crate) fn track(&self) -> Angle {
+        self.current_frame().track
+    }
+
+    // Sideslip angle (+ is the relative wind coming from the right)
+    pub(crate) fn sideslip(&self) -> Angle {
+        self.current_frame().sideslip
+    }
+
+    // The load factor needed to hold the flight path at the current pitch and bank
+    pub(crate) fn expected_load_factor(&self) -> f64 {
+        self.current_frame().expected_load_factor
+    }
+
+    // Air data computed from the static and total pressures
+    pub(crate) fn air_data(&self) -> AirData {
+        self.current_frame().air_data
+    }
+
+    // Estimated lift coefficient, from the load factor, weight and dynamic pressure
+    pub(crate) fn lift_coefficient(&self) -> f64 {
+        self.current_frame().lift_coefficient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    // A 60 fps sim frame
+    const DT: f64 = 1.0 / 60.0;
+
+    // Deterministic uniform noise in [-amplitude, amplitude]
+    struct Noise(u64);
+
+    impl Noise {
+        fn sample(&mut self, amplitude: f64) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            amplitude * (2.0 * (self.0 >> 11) as f64 / (1u64 << 53) as f64 - 1.0)
+        }
+    }
+
+    fn rms(errors: &[f64]) -> f64 {
+        (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt()
+    }
+
+    // Conditions a constant signal with noise for 10 seconds, returning the mean of the output
+    // and the RMS of the output and input noise once the filter has settled
+    fn condition_noisy(cutoff: f64, value: f64, amplitude: f64) -> (f64, f64, f64) {
+        let mut filter = LowPass::from_cutoff(cutoff);
+        let mut noise = Noise(0x2545_f491_4f6c_dd1d);
+        let (mut outputs, mut inputs) = (Vec::new(), Vec::new());
+        for frame in 0..600 {
+            let input = value + noise.sample(amplitude);
+            let (output, valid) = Data::condition(&mut filter, Some(input), 0.0, DT);
+            assert!(valid);
+            if frame >= 60 {
+                outputs.push(output - value);
+                inputs.push(input - value);
+            }
+        }
+        let mean = value + outputs.iter().sum::<f64>() / outputs.len() as f64;
+        (mean, rms(&outputs), rms(&inputs))
+    }
+
+    #[test]
+    fn aoa_noise_is_filtered() {
+        let (mean, output, input) = condition_noisy(Data::AOA_CUTOFF, 5.0, 1.0);
+
+        assert_close(mean, 5.0, 0.05);
+        assert!(output < 0.4 * input, "{} vs {}", output, input);
+    }
+
+    #[test]
+    fn gforce_noise_is_filtered() {
+        let (mean, output, input) = condition_noisy(Data::GFORCE_CUTOFF, 1.0, 0.2);
+
+        assert_close(mean, 1.0, 0.01);
+        assert!(output < 0.45 * input, "{} vs {}", output, input);
+    }
+
+    #[test]
+    fn unavailable_signal_keeps_the_last_value() {
+        let mut filter = LowPass::from_cutoff(Data::AOA_CUTOFF);
+        Data::condition(&mut filter, Some(4.0), 0.0, DT);
+
+        assert_eq!(Data::condition(&mut filter, None, 4.0, DT), (4.0, false));
+        // The filter resumes from where it was
+        let (aoa, valid) = Data::condition(&mut filter, Some(4.0), 4.0, DT);
+        assert!(valid);
+        assert_close(aoa, 4.0, 1e-12);
+    }
+
+    #[test]
+    fn pitch_rate_from_body_rates() {
+        let mut filter = LowPass::from_cutoff(Data::PITCH_RATE_CUTOFF);
+        let mut rate = |body_rates, roll| {
+            Data::condition_pitch_rate(
+                &mut filter,
+                Some(body_rates),
+                Angle::from_degrees(roll),
+                None,
+                AngularVelocity::default(),
+                DT,
+            )
+        };
+
+        // The sim body pitch rate is positive nose down
+        let (pitch_rate, valid) = rate((-2.0, 0.0), 0.0);
+        assert!(valid);
+        assert_close(pitch_rate.degrees_per_second(), 2.0, 1e-12);
+
+        // A steady level turn at 60 degrees of bank: the body pitch and yaw rates cancel out
+        let turn_rate: f64 = 3.0;
+        let bank = 60f64.to_radians();
+        let (pitch_rate, _) = rate((-turn_rate * bank.sin(), turn_rate * bank.cos()), 60.0);
+        assert_close(pitch_rate.degrees_per_second(), 0.0, 1e-12);
+
+        // Knife edge: yawing nose right is pitching down
+        let (pitch_rate, _) = rate((0.0, 3.0), 90.0);
+        assert_close(pitch_rate.degrees_per_second(), -3.0, 1e-12);
+    }
+
+    #[test]
+    fn pitch_rate_estimated_from_noisy_attitude() {
+        let mut filter = LowPass::from_cutoff(Data::PITCH_RATE_CUTOFF);
+        let mut noise = Noise(0x9e37_79b9_7f4a_7c15);
+        let (mut estimated, mut raw) = (Vec::new(), Vec::new());
+        let mut previous_pitch = Angle::default();
+        for frame in 1..600 {
+            // Pitching up at 2 degrees per second, with 0.02 degree of attitude noise
+            let pitch = Angle::from_degrees(2.0 * frame as f64 * DT + noise.sample(0.02));
+            let change = pitch - previous_pitch;
+            previous_pitch = pitch;
+            let (rate, valid) = Data::condition_pitch_rate(
+                &mut filter,
+                None,
+                Angle::default(),
+                Some(change),
+                AngularVelocity::default(),
+                DT,
+            );
+            assert!(valid);
+            if frame > 60 {
+                estimated.push(rate.degrees_per_second() - 2.0);
+                raw.push(change.degrees() / DT - 2.0);
+            }
+        }
+
+        assert_close(
+            estimated.iter().sum::<f64>() / estimated.len() as f64,
+            0.0,
+            0.05,
+        );
+        assert!(rms(&estimated) < 0.6 * rms(&raw));
+    }
+
+    #[test]
+    fn pitch_rate_unavailable() {
+        let mut filter = LowPass::from_cutoff(Data::PITCH_RATE_CUTOFF);
+        let last = AngularVelocity::from_degrees_per_second(1.5);
+
+        assert_eq!(
+            Data::condition_pitch_rate(&mut filter, None, Angle::default(), None, last, DT),
+            (last, false)
+        );
+        // The attitude can't be differentiated without time passing
+        let change = Some(Angle::from_degrees(1.0));
+        assert_eq!(
+            Data::condition_pitch_rate(&mut filter, None, Angle::default(), change, last, 0.0),
+            (last, false)
+        );
+    }
+
+    fn cruise() -> SimVars {
+        SimVars {
+            altitude: 35000.0,
+            aoa: 2.5,
+            body_pitch_rate: -1.0,
+            gforce: 1.0,
+            ias: 250.0,
+            pitch: -2.5,
+            roll: 10.0,
+            speed_longitudinal: 700.0,
+            static_pressure: 238.4,
+            tas: 450.0,
+            temperature: -54.3,
+            weight: 140000.0,
+            ..SimVars::default()
+        }
+    }
+
+    #[test]
+    fn nothing_is_valid_before_the_first_batch() {
+        let mut data = Data::default();
+        data.process(0.0, DT);
+
+        assert!(!data.received());
+        assert!(!data.alpha_valid());
+        assert!(!data.gforce_valid());
+        assert!(!data.pitch_rate_valid());
+        assert!(!data.elevator_trim_valid());
+        assert!(!data.engine_running(1));
+    }
+
+    #[test]
+    fn batch_is_converted() {
+        let mut data = Data {
+            sim_vars: Some(SimVars {
+                gforce: f64::NAN,
+                ..cruise()
+            }),
+            ..Data::default()
+        };
+        data.process(1.0, DT);
+
+        assert!(data.received());
+        assert_close(data.altitude().feet(), 35000.0, 1e-9);
+        assert_close(data.alpha().degrees(), 2.5, 1e-9);
+        assert!(data.alpha_valid());
+        assert!(!data.gforce_valid());
+        // The sim attitude and body rates are positive nose down and left wing down
+        assert_close(data.pitch().degrees(), 2.5, 1e-9);
+        assert_close(data.roll().degrees(), -10.0, 1e-9);
+        assert_close(
+            data.pitch_rate().degrees_per_second(),
+            10f64.to_radians().cos(),
+            1e-9,
+        );
+        assert_close(data.static_pressure().pascals(), 23840.0, 1e-6);
+        assert_close(data.ias().knots(), 250.0, 1e-9);
+    }
+
+    // Measures the per-frame cost of turning a batch of SimVars into a frame, which replaced the
+    // SimVar lookups by name. Run it with:
+    // cargo test --release -- --ignored --nocapture frame_cost
+    #[test]
+    #[ignore]
+    fn frame_cost() {
+        const FRAMES: u32 = 1_000_000;
+        let mut data = Data {
+            sim_vars: Some(cruise()),
+            ..Data::default()
+        };
+
+        let start = std::time::Instant::now();
+        for frame in 0..FRAMES {
+            data.process(frame as f64 * DT, DT);
+            std::hint::black_box(data.current_frame());
+        }
+        println!("{:?} per frame", start.elapsed() / FRAMES);
+    }
+
+    // A level frame with the given ground velocity and wind (north, east) in knots, and heading
+    fn moving(ground: (f64, f64), wind: (f64, f64), heading: f64) -> DataFrame {
+        DataFrame {
+            speed_lateral: Velocity::from_knots(ground.0),
+            speed_longitudinal: Velocity::from_knots(ground.1),
+            wind_lateral: Velocity::from_knots(wind.0),
+            wind_longitudinal: Velocity::from_knots(wind.1),
+            heading: Angle::from_degrees(heading),
+            ..DataFrame::default()
+        }
+    }
+
+    fn derived(mut frame: DataFrame) -> DataFrame {
+        Data::derive(&mut frame);
+        frame
+    }
+
+    #[test]
+    fn track_and_flight_path_angle() {
+        let frame = derived(DataFrame {
+            speed_vertical: Velocity::from_knots(10.0),
+            ..moving((0.0, 100.0), (0.0, 0.0), 90.0)
+        });
+
+        assert_close(frame.track.degrees(), 90.0, 1e-9);
+        // atan(10 / 100)
+        assert_close(frame.flight_path_angle.degrees(), 5.7106, 1e-4);
+
+        let frame = derived(moving((-100.0, -100.0), (0.0, 0.0), 0.0));
+        assert_close(frame.track.degrees(), 225.0, 1e-9);
+        assert_close(frame.flight_path_angle.degrees(), 0.0, 1e-9);
+    }
+
+    #[test]
+    fn sideslip() {
+        // Tracking north with no wind
+        let frame = derived(moving((100.0, 0.0), (0.0, 0.0), 0.0));
+        assert_close(frame.sideslip.degrees(), 0.0, 1e-9);
+
+        // Heading north in a 20 kt wind blowing towards the east: the air flows past from the
+        // left, asin(-20 / sqrt(100^2 + 20^2))
+        let frame = derived(moving((100.0, 0.0), (0.0, 20.0), 0.0));
+        assert_close(frame.air_velocity.north.knots(), 100.0, 1e-9);
+        assert_close(frame.air_velocity.east.knots(), -20.0, 1e-9);
+        assert_close(frame.sideslip.degrees(), -11.3099, 1e-4);
+
+        // The same relative wind, heading east
+        let frame = derived(moving((0.0, 100.0), (20.0, 0.0), 90.0));
+        assert_close(frame.sideslip.degrees(), 11.3099, 1e-4);
+
+        // Banked 90 degrees right, a climb is a relative wind from the left wing
+        let frame = derived(DataFrame {
+            roll: Angle::from_degrees(90.0),
+            speed_vertical: Velocity::from_knots(100.0),
+            ..moving((0.0, 0.0), (0.0, 0.0), 0.0)
+        });
+        assert_close(frame.sideslip.degrees(), -90.0, 1e-9);
+
+        // No airspeed, no sideslip
+        let frame = derived(moving((20.0, 0.0), (20.0, 0.0), 0.0));
+        assert_close(frame.sideslip.degrees(), 0.0, 1e-12);
+    }
+
+    #[test]
+    fn expected_load_factor() {
+        let load_factor = |pitch, roll| {
+            derived(DataFrame {
+                pitch: Angle::from_degrees(pitch),
+                roll: Angle::from_degrees(roll),
+                ..DataFrame::default()
+            })
+            .expected_load_factor
+        };
+
+        assert_close(load_factor(0.0, 0.0), 1.0, 1e-12);
+        assert_close(load_factor(0.0, 60.0), 2.0, 1e-12);
+        assert_close(load_factor(0.0, -60.0), 2.0, 1e-12);
+        assert_close(load_factor(60.0, 0.0), 0.5, 1e-12);
+        // Limited past 84 degrees of bank
+        assert_close(load_factor(0.0, 90.0), 10.0, 1e-12);
+    }
+
+    #[test]
+    fn lift_coefficient() {
+        // 200 kt at sea level, 140000 lb at 1.2 g:
+        // q = 0.5 * 0.002377 * (200 * 1.68781)^2 = 135.43 psf, CL = 1.2 * 140000 / (q * 1319.7)
+        let frame = derived(DataFrame {
+            density: Density::from_slugs_per_cubic_foot(0.002377),
+            tas: Velocity::from_knots(200.0),
+            weight: Mass::from_pounds(140000.0),
+            gforce: 1.2,
+            ..DataFrame::default()
+        });
+        assert_close(frame.lift_coefficient, 0.94, 1e-4);
+
+        // Meaningless at rest
+        let frame = derived(DataFrame {
+            density: Density::from_slugs_per_cubic_foot(0.002377),
+            weight: Mass::from_pounds(140000.0),
+            gforce: 1.0,
+            ..DataFrame::default()
+        });
+        assert_close(frame.lift_coefficient, 0.0, 1e-12);
+    }
+
+    #[test]
+    fn air_data_from_the_pitot_static_pressures() {
+        // At sea level in the ISA, the calibrated and true airspeeds are equal
+        let frame = derived(DataFrame {
+            tas: Velocity::from_knots(250.0),
+            static_pressure: Pressure::from_pascals(101_325.0),
+            temperature: Temperature::from_kelvin(288.15),
+            ..DataFrame::default()
+        });
+        assert_close(frame.air_data.cas.knots(), 250.0, 0.01);
+        assert_close(frame.air_data.tas.knots(), 250.0, 0.01);
+        assert_close(frame.air_data.mach, 0.3779, 1e-4);
     }
 }
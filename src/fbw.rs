@@ -1,12 +1,21 @@
 use crate::{
-    controls::Controls, data::Data, input::Input, pitch_control::PitchControl,
-    protections::NormalLawProtections, sim_time::SimTime, Result,
+    adr::AirDataReferences, autopilot::Autopilot, controls::Controls, data::Data,
+    flight_phase::FlightPhaseDetector, hydraulics::Hydraulics, input::Input, law::ControlLaws,
+    limits::SpeedLimits, pitch_control::PitchControl, protections::NormalLawProtections,
+    sim_time::SimTime, weight_balance::WeightAndBalance, Result,
 };
 
 pub(crate) struct FBW {
     pub(crate) sim: msfs::sim_connect::SimConnect,
     pub(crate) sim_time: SimTime,
     pub(crate) input: Input,
+    pub(crate) hydraulics: Hydraulics,
+    pub(crate) adr: AirDataReferences,
+    pub(crate) speed_limits: SpeedLimits,
+    pub(crate) weight_balance: WeightAndBalance,
+    pub(crate) flight_phase: FlightPhaseDetector,
+    pub(crate) control_laws: ControlLaws,
+    pub(crate) autopilot: Autopilot,
     pub(crate) normal_law_protections: NormalLawProtections,
     pub(crate) pitch_control: PitchControl,
     pub(crate) controls: Controls,
@@ -19,6 +28,13 @@ impl FBW {
             sim,
             sim_time: Default::default(),
             input: Default::default(),
+            hydraulics: Default::default(),
+            adr: Default::default(),
+            speed_limits: Default::default(),
+            weight_balance: Default::default(),
+            flight_phase: Default::default(),
+            control_laws: Default::default(),
+            autopilot: Default::default(),
             pitch_control: Default::default(),
             normal_law_protections: Default::default(),
             controls: Default::default(),
@@ -29,6 +45,9 @@ impl FBW {
     pub(crate) fn init(&mut self) -> Result<()> {
         self.sim_time.init();
         self.input.init(&self.sim)?;
+        self.data.init(&self.sim)?;
+        self.adr.init();
+        self.weight_balance.init()?;
         self.controls.init(&self.sim)?;
 
         Ok(())
@@ -37,15 +56,25 @@ impl FBW {
     pub(crate) fn update(&mut self) -> Result<()> {
         macro_rules! update {
             ($name:ident) => {
-                // These clones are optimized out
-                let mut tmp = self.$name.clone();
-                tmp.update(self)?;
+                // The component is moved out while it updates, so it can read the others through
+                // self. It reads as its default meanwhile, which must be cheap to create.
+                let mut tmp = std::mem::take(&mut self.$name);
+                let result = tmp.update(self);
                 self.$name = tmp;
+                result?;
             };
         }
 
         update!(sim_time);
+        update!(input);
         update!(data);
+        update!(hydraulics);
+        update!(adr);
+        update!(speed_limits);
+        update!(weight_balance);
+        update!(flight_phase);
+        update!(control_laws);
+        update!(autopilot);
         update!(normal_law_protections);
         update!(pitch_control);
         update!(controls);
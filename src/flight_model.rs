@@ -0,0 +1,75 @@
+// Values read from the sim flight model, flight_model.cfg, so that the laws use the same
+// coefficients as the aircraft they fly.
+
+use crate::Result;
+
+const FLIGHT_MODEL: &str =
+    include_str!("../A32NX/SimObjects/AirPlanes/Asobo_A320_NEO/flight_model.cfg");
+
+// The numeric value of a key in a section, e.g. CG_forward_limit in [WEIGHT_AND_BALANCE]
+pub(crate) fn value(section: &str, key: &str) -> Result<f64> {
+    match values(section, key)?.as_slice() {
+        [value] => Ok(*value),
+        _ => Err(format!("{} in [{}] is not a single value", key, section).into()),
+    }
+}
+
+// The comma separated numeric values of a key in a section, e.g. flaps-position.1 in [FLAPS.0]
+pub(crate) fn values(section: &str, key: &str) -> Result<Vec<f64>> {
+    let mut in_section = false;
+    for line in FLIGHT_MODEL.lines() {
+        // Values are followed by their description, after a ';'
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.starts_with('[') {
+            in_section = line == format!("[{}]", section);
+        } else if in_section {
+            let mut parts = line.splitn(2, '=');
+            if parts.next().map(str::trim) == Some(key) {
+                return parts
+                    .next()
+                    .unwrap_or("")
+                    .split(',')
+                    .map(|value| Ok(value.trim().parse()?))
+                    .collect();
+            }
+        }
+    }
+    Err(format!("{} not found in [{}]", key, section).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_values() {
+        assert_eq!(
+            value("WEIGHT_AND_BALANCE", "CG_forward_limit").unwrap(),
+            0.16
+        );
+        assert_eq!(
+            value("AIRPLANE_GEOMETRY", "elevator_up_limit").unwrap(),
+            16.0
+        );
+        // A tuple is not a single value
+        assert!(value("FLAPS.0", "flaps-position.4").is_err());
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!(
+            values("FLAPS.0", "flaps-position.4").unwrap(),
+            [35.0, 177.0, 1.0]
+        );
+        assert_eq!(
+            values("FLAPS.0", "flaps-position.1").unwrap(),
+            [10.0, 215.0, 0.25]
+        );
+    }
+
+    #[test]
+    fn missing_keys() {
+        assert!(value("FLAPS.0", "CG_forward_limit").is_err());
+        assert!(value("NO_SECTION", "type").is_err());
+    }
+}
@@ -0,0 +1,574 @@
+// Flight phase detection, shared by the laws and published for telemetry.
+//
+// The detection only depends on a few flight parameters sampled every frame, so it can be tested
+// offline from sequences of FlightPhaseInputs.
+
+use crate::{
+    data::Data,
+    fbw::FBW,
+    signal::{ConfirmTimer, Hysteresis},
+    units::{Angle, Force, Length, Velocity},
+    Result,
+};
+
+// The flight phases, from the cockpit preparation to the taxi in.
+// Published through A32NX_FBW_FLIGHT_PHASE, numbered in the order of declaration from 0.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum FlightPhase {
+    Preflight,    // On the ground with the engines stopped
+    Taxi,         // On the ground with the engines running
+    TakeoffRoll,  // Accelerating with takeoff thrust
+    Rotation,     // Pitching up, before the main gear leaves the ground
+    InitialClimb, // Airborne, up to the acceleration height
+    Climb,
+    Cruise,
+    Descent,
+    Approach, // Descending with the slats/flaps extended
+    Flare,    // Close to the ground, before touchdown
+    Rollout,  // Decelerating on the runway, after a landing or a rejected takeoff
+}
+
+impl FlightPhase {
+    pub(crate) fn on_ground(self) -> bool {
+        matches!(
+            self,
+            FlightPhase::Preflight
+                | FlightPhase::Taxi
+                | FlightPhase::TakeoffRoll
+                | FlightPhase::Rotation
+                | FlightPhase::Rollout
+        )
+    }
+}
+
+// The flight parameters the phase is detected from, for one frame
+#[derive(Default, Clone, Copy)]
+pub(crate) struct FlightPhaseInputs {
+    pub(crate) received: bool, // False until the sim data has been received
+    pub(crate) on_ground: bool,
+    pub(crate) radio_height: Length,
+    pub(crate) ias: Velocity,
+    pub(crate) thrust: Force, // The total thrust of both engines
+    pub(crate) flaps: u8,     // The flaps handle position
+    pub(crate) vertical_speed: Velocity,
+    pub(crate) pitch: Angle,
+}
+
+impl FlightPhaseInputs {
+    pub(crate) fn from_data(data: &Data) -> FlightPhaseInputs {
+        FlightPhaseInputs {
+            received: data.received(),
+            on_ground: data.on_ground(),
+            radio_height: data.radio_height(),
+            ias: data.ias(),
+            thrust: data.thrust(),
+            flaps: data.flaps(),
+            vertical_speed: data.vertical_speed(),
+            pitch: data.pitch(),
+        }
+    }
+}
+
+// The flight phase state machine.
+// The ground/air transitions and the thrust and vertical speed changes are confirmed over time, so
+// that gear bounces and turbulence don't make the phase flicker.
+#[derive(Clone)]
+pub(crate) struct FlightPhaseDetector {
+    pub(crate) phase: FlightPhase,
+    initialised: bool, // False until the first frame of sim data, which the phase is initialised from
+    airborne: ConfirmTimer,
+    touchdown: ConfirmTimer,
+    inconsistent: ConfirmTimer, // The phase disagrees with the sim about being on the ground
+    engines_running: ConfirmTimer,
+    engines_stopped: ConfirmTimer,
+    takeoff_thrust: Hysteresis,
+    takeoff_thrust_confirmed: ConfirmTimer,
+    rotating: Hysteresis,
+    high_speed: Hysteresis,
+    climbing: ConfirmTimer,
+    level: ConfirmTimer,
+    descending: ConfirmTimer,
+    approaching: ConfirmTimer,
+}
+impl Default for FlightPhaseDetector {
+    fn default() -> Self {
+        FlightPhaseDetector {
+            phase: FlightPhase::Preflight,
+            initialised: false,
+            airborne: ConfirmTimer::new(0.5),
+            touchdown: ConfirmTimer::new(0.5),
+            inconsistent: ConfirmTimer::new(2.0),
+            engines_running: ConfirmTimer::new(2.0),
+            engines_stopped: ConfirmTimer::new(10.0),
+            takeoff_thrust: Hysteresis::new(
+                Self::TAKEOFF_THRUST_OFF.pounds_force(),
+                Self::TAKEOFF_THRUST_ON.pounds_force(),
+            ),
+            takeoff_thrust_confirmed: ConfirmTimer::new(1.0),
+            rotating: Hysteresis::new(
+                Self::ROTATION_PITCH_OFF.degrees(),
+                Self::ROTATION_PITCH_ON.degrees(),
+            ),
+            high_speed: Hysteresis::new(Self::TAXI_SPEED.knots(), Self::ROLL_SPEED.knots()),
+            climbing: ConfirmTimer::new(10.0),
+            level: ConfirmTimer::new(30.0),
+            descending: ConfirmTimer::new(10.0),
+            approaching: ConfirmTimer::new(5.0),
+        }
+    }
+}
+
+impl FlightPhaseDetector {
+    // Below this total thrust the engines are considered stopped
+    const ENGINE_RUNNING_THRUST: Force = Force::from_pounds_force(500.0);
+    // The total thrust which is considered a takeoff (or go around) thrust, and below which it has
+    // been reduced again
+    const TAKEOFF_THRUST_ON: Force = Force::from_pounds_force(25_000.0);
+    const TAKEOFF_THRUST_OFF: Force = Force::from_pounds_force(15_000.0);
+    // The pitch attitude at which the rotation has started, and below which it has been abandoned
+    const ROTATION_PITCH_ON: Angle = Angle::from_degrees(3.0);
+    const ROTATION_PITCH_OFF: Angle = Angle::from_degrees(1.0);
+    // The speeds above which the aircraft is rolling on the runway, and below which it is taxiing
+    const ROLL_SPEED: Velocity = Velocity::from_knots(50.0);
+    const TAXI_SPEED: Velocity = Velocity::from_knots(30.0);
+    // The thrust reduction/acceleration height, which ends the initial climb
+    const ACCELERATION_HEIGHT: Length = Length::from_feet(1500.0);
+    // The height below which an approach is flown, with the slats/flaps extended
+    const APPROACH_HEIGHT: Length = Length::from_feet(2500.0);
+    // The height of the flare mode engagement, from the FCOM in 1.27.20 under "Flare Mode"
+    const FLARE_HEIGHT: Length = Length::from_feet(50.0);
+    // The vertical speed above which the aircraft is climbing or descending rather than level,
+    // 500 feet/minute
+    const LEVEL_VERTICAL_SPEED: Velocity = Velocity::from_knots(4.94);
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        use msfs::msfs::legacy::NamedVariable;
+
+        self.detect(
+            &FlightPhaseInputs::from_data(&ctx.data),
+            ctx.sim_time.delta(),
+        );
+
+        NamedVariable::from("A32NX_FBW_FLIGHT_PHASE").set_value(self.phase as u8 as f64);
+
+        Ok(())
+    }
+
+    // Updates the phase from the inputs of a frame dt seconds after the previous one
+    pub(crate) fn detect(&mut self, inputs: &FlightPhaseInputs, dt: f64) -> FlightPhase {
+        // Before the first batch the data reads as airborne at 0 feet, which would be taken as a flare
+        if !inputs.received {
+            return self.phase;
+        }
+
+        let airborne = self.airborne.update(!inputs.on_ground, dt);
+        let touchdown = self.touchdown.update(inputs.on_ground, dt);
+        let engines_running = self
+            .engines_running
+            .update(inputs.thrust > Self::ENGINE_RUNNING_THRUST, dt);
+        let engines_stopped = self
+            .engines_stopped
+            .update(inputs.thrust <= Self::ENGINE_RUNNING_THRUST, dt);
+        let takeoff_thrust = self
+            .takeoff_thrust_confirmed
+            .update(self.takeoff_thrust.update(inputs.thrust.pounds_force()), dt);
+        let rotating = self.rotating.update(inputs.pitch.degrees());
+        let high_speed = self.high_speed.update(inputs.ias.knots());
+        let climbing = self
+            .climbing
+            .update(inputs.vertical_speed > Self::LEVEL_VERTICAL_SPEED, dt);
+        let level = self.level.update(
+            inputs.vertical_speed.abs() <= Self::LEVEL_VERTICAL_SPEED,
+            dt,
+        );
+        let descending = self
+            .descending
+            .update(inputs.vertical_speed < -Self::LEVEL_VERTICAL_SPEED, dt);
+        let approaching = self.approaching.update(
+            inputs.flaps > 0
+                && inputs.radio_height < Self::APPROACH_HEIGHT
+                && inputs.vertical_speed < -Self::LEVEL_VERTICAL_SPEED,
+            dt,
+        );
+
+        if !self.initialised {
+            self.phase = Self::initial_phase(inputs);
+            self.initialised = true;
+            return self.phase;
+        }
+
+        // The sim can be repositioned at any time, so a phase which keeps disagreeing with the sim
+        // about being on the ground is detected again from scratch
+        if self
+            .inconsistent
+            .update(self.phase.on_ground() != inputs.on_ground, dt)
+        {
+            self.inconsistent.reset();
+            self.phase = Self::initial_phase(inputs);
+            return self.phase;
+        }
+
+        self.phase = match self.phase {
+            FlightPhase::Preflight if engines_running => FlightPhase::Taxi,
+            FlightPhase::Taxi if engines_stopped => FlightPhase::Preflight,
+            FlightPhase::Taxi if takeoff_thrust => FlightPhase::TakeoffRoll,
+            FlightPhase::TakeoffRoll if rotating => FlightPhase::Rotation,
+            // A rejected takeoff
+            FlightPhase::TakeoffRoll if !takeoff_thrust => FlightPhase::Rollout,
+            FlightPhase::Rotation if airborne => FlightPhase::InitialClimb,
+            FlightPhase::Rotation if !rotating => FlightPhase::TakeoffRoll,
+            FlightPhase::InitialClimb if inputs.radio_height > Self::ACCELERATION_HEIGHT => {
+                FlightPhase::Climb
+            }
+            FlightPhase::InitialClimb | FlightPhase::Climb | FlightPhase::Cruise if approaching => {
+                FlightPhase::Approach
+            }
+            FlightPhase::Climb | FlightPhase::Descent if level => FlightPhase::Cruise,
+            FlightPhase::Cruise | FlightPhase::Descent if climbing => FlightPhase::Climb,
+            FlightPhase::Climb | FlightPhase::Cruise if descending => FlightPhase::Descent,
+            FlightPhase::Descent if approaching => FlightPhase::Approach,
+            // A go around
+            FlightPhase::Approach | FlightPhase::Flare if takeoff_thrust => {
+                FlightPhase::InitialClimb
+            }
+            FlightPhase::Approach if inputs.radio_height < Self::FLARE_HEIGHT => FlightPhase::Flare,
+            FlightPhase::Flare if touchdown => FlightPhase::Rollout,
+            // A touch and go
+            FlightPhase::Rollout if takeoff_thrust => FlightPhase::TakeoffRoll,
+            FlightPhase::Rollout if !high_speed => FlightPhase::Taxi,
+            phase => phase,
+        };
+
+        self.phase
+    }
+
+    // The phase when the detection starts, which can be at any point of a flight
+    fn initial_phase(inputs: &FlightPhaseInputs) -> FlightPhase {
+        if inputs.on_ground {
+            if inputs.thrust <= Self::ENGINE_RUNNING_THRUST {
+                FlightPhase::Preflight
+            } else if inputs.thrust > Self::TAKEOFF_THRUST_ON {
+                FlightPhase::TakeoffRoll
+            } else if inputs.ias > Self::ROLL_SPEED {
+                FlightPhase::Rollout
+            } else {
+                FlightPhase::Taxi
+            }
+        } else if inputs.radio_height < Self::FLARE_HEIGHT {
+            FlightPhase::Flare
+        } else if inputs.flaps > 0 && inputs.radio_height < Self::APPROACH_HEIGHT {
+            if inputs.vertical_speed > Velocity::default() {
+                FlightPhase::InitialClimb
+            } else {
+                FlightPhase::Approach
+            }
+        } else if inputs.vertical_speed > Self::LEVEL_VERTICAL_SPEED {
+            FlightPhase::Climb
+        } else if inputs.vertical_speed < -Self::LEVEL_VERTICAL_SPEED {
+            FlightPhase::Descent
+        } else {
+            FlightPhase::Cruise
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.1;
+
+    fn parked() -> FlightPhaseInputs {
+        FlightPhaseInputs {
+            received: true,
+            on_ground: true,
+            ..FlightPhaseInputs::default()
+        }
+    }
+
+    fn cruising() -> FlightPhaseInputs {
+        FlightPhaseInputs {
+            received: true,
+            radio_height: Length::from_feet(35_000.0),
+            ias: Velocity::from_knots(270.0),
+            thrust: Force::from_pounds_force(10_000.0),
+            ..FlightPhaseInputs::default()
+        }
+    }
+
+    fn approaching() -> FlightPhaseInputs {
+        FlightPhaseInputs {
+            received: true,
+            radio_height: Length::from_feet(1500.0),
+            ias: Velocity::from_knots(140.0),
+            thrust: Force::from_pounds_force(8000.0),
+            flaps: 4,
+            vertical_speed: Velocity::from_knots(-7.0),
+            ..FlightPhaseInputs::default()
+        }
+    }
+
+    fn thrust(pounds: f64) -> Force {
+        Force::from_pounds_force(pounds)
+    }
+
+    // Feeds the same inputs for a duration, returning the phase at the end
+    fn hold(
+        detector: &mut FlightPhaseDetector,
+        inputs: FlightPhaseInputs,
+        seconds: f64,
+    ) -> FlightPhase {
+        let mut phase = detector.phase;
+        for _ in 0..(seconds / DT).round() as usize {
+            phase = detector.detect(&inputs, DT);
+        }
+        phase
+    }
+
+    // A detector which has started in the phase detected from the inputs
+    fn detector(inputs: FlightPhaseInputs) -> FlightPhaseDetector {
+        let mut detector = FlightPhaseDetector::default();
+        detector.detect(&inputs, DT);
+        detector
+    }
+
+    #[test]
+    fn initial_phase() {
+        let phase = |inputs| detector(inputs).phase;
+
+        assert_eq!(phase(parked()), FlightPhase::Preflight);
+        assert_eq!(
+            phase(FlightPhaseInputs {
+                thrust: thrust(2000.0),
+                ..parked()
+            }),
+            FlightPhase::Taxi
+        );
+        assert_eq!(
+            phase(FlightPhaseInputs {
+                thrust: thrust(2000.0),
+                ias: Velocity::from_knots(100.0),
+                ..parked()
+            }),
+            FlightPhase::Rollout
+        );
+        assert_eq!(phase(cruising()), FlightPhase::Cruise);
+        assert_eq!(phase(approaching()), FlightPhase::Approach);
+        assert_eq!(
+            phase(FlightPhaseInputs {
+                radio_height: Length::from_feet(30.0),
+                ..approaching()
+            }),
+            FlightPhase::Flare
+        );
+    }
+
+    #[test]
+    fn waits_for_the_sim_data() {
+        let mut detector = FlightPhaseDetector::default();
+
+        // A ground start, whose first frames are empty
+        assert_eq!(
+            hold(&mut detector, FlightPhaseInputs::default(), 3.0),
+            FlightPhase::Preflight
+        );
+        let taxi = FlightPhaseInputs {
+            thrust: thrust(2000.0),
+            ..parked()
+        };
+        assert_eq!(hold(&mut detector, taxi, DT), FlightPhase::Taxi);
+        assert_eq!(hold(&mut detector, taxi, 3.0), FlightPhase::Taxi);
+    }
+
+    #[test]
+    fn full_flight() {
+        let mut detector = detector(parked());
+
+        // Engine start, and the engines confirmed running
+        let taxi = FlightPhaseInputs {
+            thrust: thrust(2000.0),
+            ias: Velocity::from_knots(15.0),
+            ..parked()
+        };
+        assert_eq!(hold(&mut detector, taxi, 1.0), FlightPhase::Preflight);
+        assert_eq!(hold(&mut detector, taxi, 1.5), FlightPhase::Taxi);
+
+        // Takeoff thrust, confirmed after a second
+        let takeoff = FlightPhaseInputs {
+            thrust: thrust(45_000.0),
+            ias: Velocity::from_knots(80.0),
+            ..taxi
+        };
+        assert_eq!(hold(&mut detector, takeoff, 0.5), FlightPhase::Taxi);
+        assert_eq!(hold(&mut detector, takeoff, 1.0), FlightPhase::TakeoffRoll);
+
+        let rotation = FlightPhaseInputs {
+            ias: Velocity::from_knots(145.0),
+            pitch: Angle::from_degrees(5.0),
+            ..takeoff
+        };
+        assert_eq!(hold(&mut detector, rotation, 1.0), FlightPhase::Rotation);
+
+        // Liftoff, confirmed after half a second
+        let initial_climb = FlightPhaseInputs {
+            on_ground: false,
+            radio_height: Length::from_feet(100.0),
+            pitch: Angle::from_degrees(15.0),
+            vertical_speed: Velocity::from_knots(20.0),
+            ..rotation
+        };
+        assert_eq!(
+            hold(&mut detector, initial_climb, 0.3),
+            FlightPhase::Rotation
+        );
+        assert_eq!(
+            hold(&mut detector, initial_climb, 0.3),
+            FlightPhase::InitialClimb
+        );
+
+        let climb = FlightPhaseInputs {
+            radio_height: Length::from_feet(2000.0),
+            thrust: thrust(20_000.0),
+            ..initial_climb
+        };
+        assert_eq!(hold(&mut detector, climb, 1.0), FlightPhase::Climb);
+
+        // Level for 30 seconds
+        assert_eq!(hold(&mut detector, cruising(), 20.0), FlightPhase::Climb);
+        assert_eq!(hold(&mut detector, cruising(), 10.5), FlightPhase::Cruise);
+
+        let descent = FlightPhaseInputs {
+            vertical_speed: Velocity::from_knots(-20.0),
+            ..cruising()
+        };
+        assert_eq!(hold(&mut detector, descent, 10.5), FlightPhase::Descent);
+
+        assert_eq!(
+            hold(&mut detector, approaching(), 5.5),
+            FlightPhase::Approach
+        );
+
+        let flare = FlightPhaseInputs {
+            radio_height: Length::from_feet(30.0),
+            ..approaching()
+        };
+        assert_eq!(hold(&mut detector, flare, 1.0), FlightPhase::Flare);
+
+        // Touchdown, confirmed after half a second
+        let rollout = FlightPhaseInputs {
+            on_ground: true,
+            radio_height: Length::default(),
+            vertical_speed: Velocity::default(),
+            ..flare
+        };
+        assert_eq!(hold(&mut detector, rollout, 1.0), FlightPhase::Rollout);
+
+        let vacated = FlightPhaseInputs {
+            ias: Velocity::from_knots(20.0),
+            ..rollout
+        };
+        assert_eq!(hold(&mut detector, vacated, 1.0), FlightPhase::Taxi);
+
+        // Engine shutdown, confirmed after 10 seconds
+        assert_eq!(hold(&mut detector, parked(), 5.0), FlightPhase::Taxi);
+        assert_eq!(hold(&mut detector, parked(), 5.5), FlightPhase::Preflight);
+    }
+
+    #[test]
+    fn rejected_takeoff() {
+        let takeoff = FlightPhaseInputs {
+            thrust: thrust(45_000.0),
+            ias: Velocity::from_knots(100.0),
+            ..parked()
+        };
+        let mut detector = detector(takeoff);
+        assert_eq!(detector.phase, FlightPhase::TakeoffRoll);
+
+        let rejected = FlightPhaseInputs {
+            thrust: thrust(5000.0),
+            ..takeoff
+        };
+        assert_eq!(hold(&mut detector, rejected, 0.5), FlightPhase::Rollout);
+    }
+
+    #[test]
+    fn abandoned_rotation() {
+        let rotation = FlightPhaseInputs {
+            thrust: thrust(45_000.0),
+            ias: Velocity::from_knots(145.0),
+            pitch: Angle::from_degrees(5.0),
+            ..parked()
+        };
+        let mut detector = detector(rotation);
+        assert_eq!(hold(&mut detector, rotation, 0.5), FlightPhase::Rotation);
+
+        // The hysteresis holds the rotation down to 1 degree
+        let lowered = FlightPhaseInputs {
+            pitch: Angle::from_degrees(2.0),
+            ..rotation
+        };
+        assert_eq!(hold(&mut detector, lowered, 0.5), FlightPhase::Rotation);
+        let lowered = FlightPhaseInputs {
+            pitch: Angle::from_degrees(0.5),
+            ..rotation
+        };
+        assert_eq!(hold(&mut detector, lowered, 0.5), FlightPhase::TakeoffRoll);
+    }
+
+    #[test]
+    fn go_around() {
+        let mut detector = detector(approaching());
+
+        let go_around = FlightPhaseInputs {
+            thrust: thrust(45_000.0),
+            vertical_speed: Velocity::from_knots(10.0),
+            ..approaching()
+        };
+        assert_eq!(
+            hold(&mut detector, go_around, 1.5),
+            FlightPhase::InitialClimb
+        );
+    }
+
+    #[test]
+    fn touch_and_go() {
+        let rollout = FlightPhaseInputs {
+            thrust: thrust(8000.0),
+            ias: Velocity::from_knots(120.0),
+            ..parked()
+        };
+        let mut detector = detector(rollout);
+        assert_eq!(detector.phase, FlightPhase::Rollout);
+
+        let takeoff = FlightPhaseInputs {
+            thrust: thrust(45_000.0),
+            ..rollout
+        };
+        assert_eq!(hold(&mut detector, takeoff, 1.5), FlightPhase::TakeoffRoll);
+    }
+
+    #[test]
+    fn bounces_are_filtered() {
+        let flare = FlightPhaseInputs {
+            radio_height: Length::from_feet(10.0),
+            ..approaching()
+        };
+        let mut detector = detector(flare);
+
+        let bounce = FlightPhaseInputs {
+            on_ground: true,
+            ..flare
+        };
+        assert_eq!(hold(&mut detector, bounce, 0.3), FlightPhase::Flare);
+        assert_eq!(hold(&mut detector, flare, 1.0), FlightPhase::Flare);
+    }
+
+    #[test]
+    fn reposition() {
+        let mut detector = detector(cruising());
+
+        // Moved to the gate by the sim: the phase is detected again once it keeps disagreeing
+        assert_eq!(hold(&mut detector, parked(), 1.5), FlightPhase::Cruise);
+        assert_eq!(hold(&mut detector, parked(), 1.0), FlightPhase::Preflight);
+    }
+}
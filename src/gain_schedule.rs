@@ -0,0 +1,231 @@
+use crate::{clamp, signal::Table2D, units::Pressure, Result};
+use std::collections::HashMap;
+
+// The controllers whose gains are scheduled
+#[derive(PartialEq, Eq, Hash, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ScheduledController {
+    AngleOfAttack,
+    LoadFactor,
+    PitchRate,
+    Roll,
+}
+
+// A row of the gain schedule configuration
+#[derive(serde::Deserialize)]
+struct GainRecord {
+    controller: ScheduledController,
+    flaps: u8,             // The flaps handle position
+    dynamic_pressure: f64, // The dynamic pressure in pounds per square foot
+    mach: f64,             // The speed in mach
+    scale: f64,            // The factor applied to the controller gains
+}
+
+// Gain scaling of the control law controllers by dynamic pressure, mach and flaps configuration.
+// The baseline gains are multiplied by a factor interpolated from gain_schedule.csv, which is
+// installed next to the gauge and loaded at init, so that it can be tuned without rebuilding.
+#[derive(Default, Clone)]
+pub(crate) struct GainSchedule {
+    tables: HashMap<(ScheduledController, u8), Table2D>,
+}
+
+impl GainSchedule {
+    // The schedule file, relative to the package root
+    const PATH: &'static str = "SimObjects/AirPlanes/Asobo_A320_NEO/panel/gain_schedule.csv";
+
+    // Loads the schedule file. If it is missing or malformed, the error is reported and the laws
+    // are flown with their baseline gains, rather than without flight controls.
+    pub(crate) fn load() -> GainSchedule {
+        Self::load_from(Self::PATH)
+    }
+
+    fn load_from(path: &str) -> GainSchedule {
+        match Self::read(path) {
+            Ok(schedule) => schedule,
+            Err(error) => {
+                eprintln!("A32NX_FBW: flying the baseline gains, {}", error);
+                GainSchedule::default()
+            }
+        }
+    }
+
+    fn read(path: &str) -> Result<GainSchedule> {
+        let file = std::fs::File::open(path)
+            .map_err(|error| format!("cannot open {}: {}", path, error))?;
+        Self::from_reader(file).map_err(|error| format!("cannot read {}: {}", path, error).into())
+    }
+
+    fn from_reader<R: std::io::Read>(reader: R) -> Result<GainSchedule> {
+        let mut points: HashMap<(ScheduledController, u8), Vec<(f64, f64, f64)>> = HashMap::new();
+        let mut reader = csv::Reader::from_reader(reader);
+        for record in reader.deserialize() {
+            let record: GainRecord = record?;
+            points
+                .entry((record.controller, record.flaps))
+                .or_default()
+                .push((record.dynamic_pressure, record.mach, record.scale));
+        }
+
+        let mut tables = HashMap::new();
+        for (key, points) in points {
+            tables.insert(key, Table2D::from_points(&points)?);
+        }
+
+        Ok(GainSchedule { tables })
+    }
+
+    // The gain factor for a controller, or 1.0 if it isn't scheduled in this configuration
+    pub(crate) fn scale(
+        &self,
+        controller: ScheduledController,
+        flaps: u8,
+        dynamic_pressure: Pressure,
+        mach: f64,
+    ) -> f64 {
+        match self.tables.get(&(controller, flaps)) {
+            Some(table) => clamp(
+                table.interpolate(dynamic_pressure.pounds_per_square_foot(), mach),
+                0.0,
+                10.0,
+            ),
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    const CONTROLLERS: [ScheduledController; 4] = [
+        ScheduledController::AngleOfAttack,
+        ScheduledController::LoadFactor,
+        ScheduledController::PitchRate,
+        ScheduledController::Roll,
+    ];
+
+    fn installed() -> GainSchedule {
+        GainSchedule::from_reader(
+            include_str!("../A32NX/SimObjects/AirPlanes/Asobo_A320_NEO/panel/gain_schedule.csv")
+                .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    fn psf(pounds_per_square_foot: f64) -> Pressure {
+        Pressure::from_pounds_per_square_foot(pounds_per_square_foot)
+    }
+
+    #[test]
+    fn interpolation() {
+        let schedule = GainSchedule::from_reader(
+            "controller,flaps,dynamic_pressure,mach,scale\n\
+             pitch_rate,0,100,0.3,2.0\n\
+             pitch_rate,0,100,0.8,3.0\n\
+             pitch_rate,0,300,0.3,1.0\n\
+             pitch_rate,0,300,0.8,1.5\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        let scale = |q, mach| schedule.scale(ScheduledController::PitchRate, 0, psf(q), mach);
+
+        // On the grid
+        assert_close(scale(100.0, 0.3), 2.0, 1e-9);
+        assert_close(scale(300.0, 0.8), 1.5, 1e-9);
+        // Between the grid points
+        assert_close(scale(200.0, 0.3), 1.5, 1e-9);
+        assert_close(scale(100.0, 0.55), 2.5, 1e-9);
+        assert_close(scale(200.0, 0.55), 1.875, 1e-9);
+        // Held at the edges
+        assert_close(scale(50.0, 0.3), 2.0, 1e-9);
+        assert_close(scale(500.0, 0.9), 1.5, 1e-9);
+    }
+
+    #[test]
+    fn unscheduled_controllers_are_not_scaled() {
+        let schedule = installed();
+
+        assert_close(
+            schedule.scale(ScheduledController::Roll, 9, psf(200.0), 0.5),
+            1.0,
+            1e-9,
+        );
+        assert_close(
+            GainSchedule::default().scale(ScheduledController::Roll, 0, psf(200.0), 0.5),
+            1.0,
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn incomplete_grid_is_rejected() {
+        let schedule = GainSchedule::from_reader(
+            "controller,flaps,dynamic_pressure,mach,scale\n\
+             roll,0,100,0.3,2.0\n\
+             roll,0,300,0.8,1.5\n"
+                .as_bytes(),
+        );
+
+        assert!(schedule.is_err());
+    }
+
+    #[test]
+    fn non_finite_fields_are_rejected() {
+        let schedule = GainSchedule::from_reader(
+            "controller,flaps,dynamic_pressure,mach,scale\n\
+             load_factor,0,100,0.3,2.0\n\
+             load_factor,0,100,0.8,3.0\n\
+             load_factor,0,NaN,0.3,1.0\n\
+             load_factor,0,NaN,0.8,1.5\n"
+                .as_bytes(),
+        );
+
+        assert!(schedule.is_err());
+    }
+
+    #[test]
+    fn installed_schedule_is_smooth() {
+        let schedule = installed();
+
+        // Every controller is scheduled in every configuration, and its scale changes by less
+        // than 2% per pound/square foot and 5% per 0.05 mach, over and beyond the envelope
+        for &controller in CONTROLLERS.iter() {
+            for flaps in 0..=4 {
+                let scale = |q: f64, mach: f64| schedule.scale(controller, flaps, psf(q), mach);
+                for q in (0..600).map(f64::from) {
+                    for mach in (0..20).map(|m| f64::from(m) * 0.05) {
+                        let value = scale(q, mach);
+                        assert!(value > 0.0 && value <= 10.0);
+                        assert!((scale(q + 1.0, mach) - value).abs() <= 0.02 * value);
+                        assert!((scale(q, mach + 0.05) - value).abs() <= 0.05 * value);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unavailable_schedule_flies_the_baseline_gains() {
+        let missing = GainSchedule::load_from("missing_gain_schedule.csv");
+        assert_close(
+            missing.scale(ScheduledController::PitchRate, 0, psf(200.0), 0.5),
+            1.0,
+            1e-9,
+        );
+
+        let path = std::env::temp_dir().join("a32nx_fbw_malformed_gain_schedule.csv");
+        std::fs::write(
+            &path,
+            "controller,flaps,dynamic_pressure,mach,scale\npitch_rate,0,fast,0.3,2.0\n",
+        )
+        .unwrap();
+        let malformed = GainSchedule::load_from(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_close(
+            malformed.scale(ScheduledController::PitchRate, 0, psf(200.0), 0.5),
+            1.0,
+            1e-9,
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Fixed capacity history of the last frames, with queries over time windows.
+//
+// Not every query is used by the laws yet
+#![allow(dead_code)]
+
+// A frame that knows when it was recorded
+pub(crate) trait Timestamped {
+    // The time of the frame in seconds
+    fn time(&self) -> f64;
+}
+
+// Ring buffer of frames: pushing is O(1) and overwrites the oldest frame once full.
+// The storage is only allocated by the first push, so an empty history is free to create.
+#[derive(Clone)]
+pub(crate) struct FrameHistory<T> {
+    frames: Vec<T>,
+    capacity: usize,
+    next: usize, // Index the next frame is written to, once full
+}
+
+impl<T> FrameHistory<T> {
+    pub(crate) fn new(capacity: usize) -> FrameHistory<T> {
+        debug_assert!(capacity > 0);
+        FrameHistory {
+            frames: Vec::new(),
+            capacity,
+            next: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, frame: T) {
+        if self.frames.len() < self.capacity {
+            if self.frames.is_empty() {
+                self.frames.reserve_exact(self.capacity);
+            }
+            self.frames.push(frame);
+        } else {
+            self.frames[self.next] = frame;
+            self.next = (self.next + 1) % self.capacity;
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // The frame recorded n frames ago, 0 being the latest
+    pub(crate) fn ago(&self, n: usize) -> Option<&T> {
+        if n >= self.frames.len() {
+            return None;
+        }
+        let latest = if self.frames.len() < self.capacity {
+            self.frames.len() - 1
+        } else {
+            (self.next + self.capacity - 1) % self.capacity
+        };
+        Some(&self.frames[(latest + self.capacity - n) % self.capacity])
+    }
+
+    pub(crate) fn latest(&self) -> Option<&T> {
+        self.ago(0)
+    }
+
+    // The frames from the latest to the oldest
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..self.frames.len()).filter_map(move |n| self.ago(n))
+    }
+}
+
+impl<T: Timestamped> FrameHistory<T> {
+    // The frames recorded in the last `seconds` before the latest one, from the latest
+    fn window(&self, seconds: f64) -> impl Iterator<Item = &T> {
+        let start = self.latest().map_or(0.0, |latest| latest.time() - seconds);
+        self.iter().take_while(move |frame| frame.time() >= start)
+    }
+
+    pub(crate) fn mean(&self, seconds: f64, value: impl Fn(&T) -> f64) -> Option<f64> {
+        let (sum, count) = self.window(seconds).fold((0.0, 0), |(sum, count), frame| {
+            (sum + value(frame), count + 1)
+        });
+        if count > 0 {
+            Some(sum / count as f64)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn min(&self, seconds: f64, value: impl Fn(&T) -> f64) -> Option<f64> {
+        self.window(seconds)
+            .map(value)
+            .fold(None, |min, v| match min {
+                Some(min) if min <= v => Some(min),
+                _ => Some(v),
+            })
+    }
+
+    pub(crate) fn max(&self, seconds: f64, value: impl Fn(&T) -> f64) -> Option<f64> {
+        self.window(seconds)
+            .map(value)
+            .fold(None, |max, v| match max {
+                Some(max) if max >= v => Some(max),
+                _ => Some(v),
+            })
+    }
+
+    // Rate of change (per second) of a value over the window, fitted by least squares
+    pub(crate) fn slope(&self, seconds: f64, value: impl Fn(&T) -> f64) -> Option<f64> {
+        // Times are taken relative to the latest frame, to keep the sums well conditioned
+        let origin = self.latest()?.time();
+        let (mut n, mut sum_t, mut sum_v, mut sum_tt, mut sum_tv) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for frame in self.window(seconds) {
+            let t = frame.time() - origin;
+            let v = value(frame);
+            n += 1.0;
+            sum_t += t;
+            sum_v += v;
+            sum_tt += t * t;
+            sum_tv += t * v;
+        }
+
+        let denominator = n * sum_tt - sum_t * sum_t;
+        if n < 2.0 || denominator <= 0.0 {
+            return None;
+        }
+        Some((n * sum_tv - sum_t * sum_v) / denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Frame {
+        time: f64,
+        value: f64,
+    }
+
+    impl Timestamped for Frame {
+        fn time(&self) -> f64 {
+            self.time
+        }
+    }
+
+    // A history of frames every 0.1 s, the value increasing by 1.0 per frame
+    fn history(capacity: usize, frames: usize) -> FrameHistory<Frame> {
+        let mut history = FrameHistory::new(capacity);
+        for n in 0..frames {
+            history.push(Frame {
+                time: n as f64 * 0.1,
+                value: n as f64,
+            });
+        }
+        history
+    }
+
+    fn values(history: &FrameHistory<Frame>) -> Vec<f64> {
+        history.iter().map(|frame| frame.value).collect()
+    }
+
+    #[test]
+    fn empty() {
+        let history = history(3, 0);
+
+        assert!(history.is_empty());
+        assert_eq!(history.latest(), None);
+        assert_eq!(history.mean(1.0, |frame| frame.value), None);
+        assert_eq!(history.slope(1.0, |frame| frame.value), None);
+    }
+
+    #[test]
+    fn filling() {
+        let history = history(3, 2);
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(values(&history), [1.0, 0.0]);
+        assert_eq!(history.ago(1).map(|frame| frame.value), Some(0.0));
+        assert_eq!(history.ago(2), None);
+    }
+
+    #[test]
+    fn wraparound() {
+        for frames in 3..10 {
+            let history = history(3, frames);
+            let latest = (frames - 1) as f64;
+
+            assert_eq!(history.len(), 3);
+            assert_eq!(values(&history), [latest, latest - 1.0, latest - 2.0]);
+            assert_eq!(history.ago(3), None);
+        }
+    }
+
+    #[test]
+    fn windows_across_the_wraparound() {
+        // The latest frame is at 1.0 s, the oldest kept at 0.6 s, and the storage has wrapped
+        let history = history(5, 11);
+        let value = |frame: &Frame| frame.value;
+
+        // The last 0.25 s holds the frames at 1.0, 0.9 and 0.8 s
+        assert_close(history.mean(0.25, value).unwrap(), 9.0, 1e-12);
+        assert_close(history.min(0.25, value).unwrap(), 8.0, 1e-12);
+        assert_close(history.max(0.25, value).unwrap(), 10.0, 1e-12);
+        assert_close(history.slope(0.25, value).unwrap(), 10.0, 1e-9);
+        // Windows longer than the history hold every frame
+        assert_close(history.mean(10.0, value).unwrap(), 8.0, 1e-12);
+        assert_close(history.min(10.0, value).unwrap(), 6.0, 1e-12);
+        // A slope needs two frames
+        assert_eq!(history.slope(0.05, value), None);
+    }
+
+    // Compares the ring buffer with the previous scheme, which rotated a Vec of frames and cloned
+    // the whole history every frame. Run it with:
+    // cargo test --release -- --ignored --nocapture history_cost
+    #[test]
+    #[ignore]
+    fn history_cost() {
+        // About the size of a DataFrame
+        type Payload = [f64; 64];
+        const CAPACITY: usize = 50;
+        const FRAMES: u32 = 1_000_000;
+
+        let mut ring = FrameHistory::new(CAPACITY);
+        let start = std::time::Instant::now();
+        for frame in 0..FRAMES {
+            ring.push([frame as f64; 64]);
+            std::hint::black_box(ring.latest());
+        }
+        let ring_cost = start.elapsed() / FRAMES;
+
+        let mut rotated: Vec<Payload> = vec![[0.0; 64]; CAPACITY];
+        let start = std::time::Instant::now();
+        for frame in 0..FRAMES {
+            let mut copy = rotated.clone();
+            copy.rotate_right(1);
+            copy[0] = [frame as f64; 64];
+            rotated = copy;
+            std::hint::black_box(&rotated[0]);
+        }
+        let rotated_cost = start.elapsed() / FRAMES;
+
+        println!(
+            "ring buffer: {:?} per frame, rotated and cloned Vec: {:?} per frame",
+            ring_cost, rotated_cost
+        );
+    }
+}
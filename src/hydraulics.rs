@@ -0,0 +1,103 @@
+use crate::{fbw::FBW, Result};
+
+#[derive(PartialEq, Clone, Copy)]
+pub(crate) enum HydraulicSystem {
+    Green,
+    Blue,
+    Yellow,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Hydraulics {
+    green: bool,
+    blue: bool,
+    yellow: bool,
+}
+
+impl Hydraulics {
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        let failed = |name| {
+            use msfs::msfs::legacy::*;
+            NamedVariable::from(name).get_value::<f64>() != 0.0
+        };
+
+        // This is a simplified model of the hydraulic power generation:
+        // - Green is pressurised by the engine #1 pump, Yellow by the engine #2 pump
+        // - The PTU pressurises Green or Yellow when only the opposite engine is running
+        // - Blue is pressurised by its electric pump as soon as one engine is running
+        // A system can additionally be failed through its failure L:var (e.g. to simulate a reservoir loss).
+        let engine_1 = ctx.data.engine_running(1);
+        let engine_2 = ctx.data.engine_running(2);
+        let any_engine = engine_1 || engine_2;
+        let green_failed = failed("A32NX_FBW_FAILURE_HYD_GREEN");
+        let blue_failed = failed("A32NX_FBW_FAILURE_HYD_BLUE");
+        let yellow_failed = failed("A32NX_FBW_FAILURE_HYD_YELLOW");
+
+        // The PTU only transfers power, so it is lost if either side has failed
+        let ptu = !green_failed && !yellow_failed;
+        self.green = !green_failed && (engine_1 || (ptu && engine_2));
+        self.yellow = !yellow_failed && (engine_2 || (ptu && engine_1));
+        self.blue = !blue_failed && any_engine;
+
+        Ok(())
+    }
+
+    // The systems as pressurised, without their power generation
+    #[cfg(test)]
+    pub(crate) fn pressurised(green: bool, blue: bool, yellow: bool) -> Hydraulics {
+        Hydraulics {
+            green,
+            blue,
+            yellow,
+        }
+    }
+
+    pub(crate) fn is_pressurised(&self, system: HydraulicSystem) -> bool {
+        match system {
+            HydraulicSystem::Green => self.green,
+            HydraulicSystem::Blue => self.blue,
+            HydraulicSystem::Yellow => self.yellow,
+        }
+    }
+
+    // The actuator supplies below come from the FCOM in 1.27.10 under "Description"
+
+    // Left elevator: Blue and Green, right elevator: Yellow and Blue
+    pub(crate) fn left_elevator_available(&self) -> bool {
+        self.blue || self.green
+    }
+
+    pub(crate) fn right_elevator_available(&self) -> bool {
+        self.yellow || self.blue
+    }
+
+    // Each aileron has a Green and a Blue actuator
+    pub(crate) fn ailerons_available(&self) -> bool {
+        self.green || self.blue
+    }
+
+    // The rudder is powered by all three systems
+    pub(crate) fn rudder_available(&self) -> bool {
+        self.green || self.blue || self.yellow
+    }
+
+    // The THS is driven by two hydraulic motors, powered by Green and Yellow
+    pub(crate) fn ths_available(&self) -> bool {
+        self.green || self.yellow
+    }
+
+    // Spoilers 1 to 5 are powered by Green, Yellow, Blue, Yellow and Green, on both wings
+    pub(crate) fn spoiler_available(&self, panel: usize) -> bool {
+        match panel {
+            1 | 5 => self.green,
+            2 | 4 => self.yellow,
+            3 => self.blue,
+            _ => false,
+        }
+    }
+
+    // The nose wheel steering is powered by Yellow, from the FCOM in 1.32.30 under "Description"
+    pub(crate) fn nosewheel_steering_available(&self) -> bool {
+        self.yellow
+    }
+}
@@ -1,19 +1,44 @@
-use crate::Result;
+use crate::{
+    fbw::FBW,
+    shaping::{AxisConfig, AxisShaping, InputAxis},
+    sidestick::{Priority, Sidestick, SidestickPriority},
+    Result,
+};
 use msfs::{
+    msfs::legacy::NamedVariable,
     sim_connect::SimConnect,
-    sys::{SIMCONNECT_GROUP_PRIORITY_HIGHEST_MASKABLE, SIMCONNECT_RECV_EVENT},
+    sys::{
+        SIMCONNECT_EVENT_FLAG_GROUPID_IS_PRIORITY, SIMCONNECT_GROUP_PRIORITY_HIGHEST,
+        SIMCONNECT_GROUP_PRIORITY_HIGHEST_MASKABLE, SIMCONNECT_OBJECT_ID_USER,
+        SIMCONNECT_RECV_EVENT,
+    },
 };
 
 #[repr(u32)]
 enum EventID {
     // Elevator Group
-    ElevatorSet, // AXIS_ELEVATOR_SET
+    ElevatorSet,  // AXIS_ELEVATOR_SET
+    ElevatorUp,   // ELEV_UP
+    ElevatorDown, // ELEV_DOWN
     // Aileron Group
     AileronsSet,          // AXIS_AILERONS_SET
     CenterAileronsRudder, // CENTER_AILER_RUDDER
+    AileronsLeft,         // AILERONS_LEFT
+    AileronsRight,        // AILERONS_RIGHT
     // Rudder group
     RudderSet,    // AXIS_RUDDER_SET
     RudderCenter, // RUDDER_CENTER
+    RudderLeft,   // RUDDER_LEFT
+    RudderRight,  // RUDDER_RIGHT
+    // Sidestick group
+    CaptainSidestickPitchSet,      // A32NX.FBW_CAPT_SIDESTICK_PITCH_SET
+    CaptainSidestickRollSet,       // A32NX.FBW_CAPT_SIDESTICK_ROLL_SET
+    CaptainTakeoverSet,            // A32NX.FBW_CAPT_TAKEOVER_SET
+    FirstOfficerSidestickPitchSet, // A32NX.FBW_FO_SIDESTICK_PITCH_SET
+    FirstOfficerSidestickRollSet,  // A32NX.FBW_FO_SIDESTICK_ROLL_SET
+    FirstOfficerTakeoverSet,       // A32NX.FBW_FO_TAKEOVER_SET
+    // Autopilot (only transmitted)
+    AutopilotOff, // AUTOPILOT_OFF
 }
 impl From<u32> for EventID {
     fn from(v: u32) -> Self {
@@ -26,6 +51,17 @@ enum GroupID {
     Elevator,
     Ailerons,
     Rudder,
+    Sidesticks,
+}
+
+// The L:vars of the inputs, registered in init
+struct InputVariables {
+    pitch: AxisConfig,
+    roll: AxisConfig,
+    yaw: AxisConfig,
+    keyboard_rate: NamedVariable,
+    sidestick_priority: NamedVariable,
+    dual_input: NamedVariable,
 }
 
 #[derive(Default)]
@@ -33,10 +69,29 @@ pub(crate) struct Input {
     pub(crate) yoke_y: f64, // -1 is full down, and +1 is full up
     pub(crate) yoke_x: f64, // -1 is full left, and +1 is full right
     pub(crate) rudder: f64, // -1 is full left, and +1 is full right
+    // The standard axis and keyboard events are assigned to the captain's sidestick
+    captain_pitch: InputAxis,
+    captain_roll: InputAxis,
+    captain_takeover: bool,
+    first_officer_pitch: InputAxis,
+    first_officer_roll: InputAxis,
+    first_officer_takeover: bool,
+    rudder_pedals: InputAxis,
+    sidestick_priority: SidestickPriority,
+    variables: Option<InputVariables>,
 }
 
 impl Input {
-    pub(crate) fn init(&self, sim: &SimConnect) -> Result<()> {
+    pub(crate) fn init(&mut self, sim: &SimConnect) -> Result<()> {
+        self.variables = Some(InputVariables {
+            pitch: AxisConfig::new("PITCH"),
+            roll: AxisConfig::new("ROLL"),
+            yaw: AxisConfig::new("YAW"),
+            keyboard_rate: NamedVariable::from("A32NX_FBW_KEYBOARD_RATE"),
+            sidestick_priority: NamedVariable::from("A32NX_FBW_SIDESTICK_PRIORITY"),
+            dual_input: NamedVariable::from("A32NX_FBW_DUAL_INPUT"),
+        });
+
         // Elevator group
         sim.map_client_event_to_sim_event(EventID::ElevatorSet as u32, "AXIS_ELEVATOR_SET")?;
         sim.add_client_event_to_notification_group(
@@ -44,6 +99,18 @@ impl Input {
             EventID::ElevatorSet as u32,
             true,
         )?;
+        sim.map_client_event_to_sim_event(EventID::ElevatorUp as u32, "ELEV_UP")?;
+        sim.add_client_event_to_notification_group(
+            GroupID::Elevator as u32,
+            EventID::ElevatorUp as u32,
+            true,
+        )?;
+        sim.map_client_event_to_sim_event(EventID::ElevatorDown as u32, "ELEV_DOWN")?;
+        sim.add_client_event_to_notification_group(
+            GroupID::Elevator as u32,
+            EventID::ElevatorDown as u32,
+            true,
+        )?;
 
         // Ailerons group
         sim.map_client_event_to_sim_event(EventID::AileronsSet as u32, "AXIS_AILERONS_SET")?;
@@ -61,6 +128,18 @@ impl Input {
             EventID::CenterAileronsRudder as u32,
             true,
         )?;
+        sim.map_client_event_to_sim_event(EventID::AileronsLeft as u32, "AILERONS_LEFT")?;
+        sim.add_client_event_to_notification_group(
+            GroupID::Ailerons as u32,
+            EventID::AileronsLeft as u32,
+            true,
+        )?;
+        sim.map_client_event_to_sim_event(EventID::AileronsRight as u32, "AILERONS_RIGHT")?;
+        sim.add_client_event_to_notification_group(
+            GroupID::Ailerons as u32,
+            EventID::AileronsRight as u32,
+            true,
+        )?;
 
         // Rudder group
         sim.map_client_event_to_sim_event(EventID::RudderSet as u32, "AXIS_RUDDER_SET")?;
@@ -75,6 +154,53 @@ impl Input {
             EventID::RudderCenter as u32,
             true,
         )?;
+        sim.map_client_event_to_sim_event(EventID::RudderLeft as u32, "RUDDER_LEFT")?;
+        sim.add_client_event_to_notification_group(
+            GroupID::Rudder as u32,
+            EventID::RudderLeft as u32,
+            true,
+        )?;
+        sim.map_client_event_to_sim_event(EventID::RudderRight as u32, "RUDDER_RIGHT")?;
+        sim.add_client_event_to_notification_group(
+            GroupID::Rudder as u32,
+            EventID::RudderRight as u32,
+            true,
+        )?;
+
+        // Sidestick group
+        let sidestick_events = [
+            (
+                EventID::CaptainSidestickPitchSet as u32,
+                "A32NX.FBW_CAPT_SIDESTICK_PITCH_SET",
+            ),
+            (
+                EventID::CaptainSidestickRollSet as u32,
+                "A32NX.FBW_CAPT_SIDESTICK_ROLL_SET",
+            ),
+            (
+                EventID::CaptainTakeoverSet as u32,
+                "A32NX.FBW_CAPT_TAKEOVER_SET",
+            ),
+            (
+                EventID::FirstOfficerSidestickPitchSet as u32,
+                "A32NX.FBW_FO_SIDESTICK_PITCH_SET",
+            ),
+            (
+                EventID::FirstOfficerSidestickRollSet as u32,
+                "A32NX.FBW_FO_SIDESTICK_ROLL_SET",
+            ),
+            (
+                EventID::FirstOfficerTakeoverSet as u32,
+                "A32NX.FBW_FO_TAKEOVER_SET",
+            ),
+        ];
+        for (id, name) in sidestick_events.iter() {
+            sim.map_client_event_to_sim_event(*id, name)?;
+            sim.add_client_event_to_notification_group(GroupID::Sidesticks as u32, *id, false)?;
+        }
+
+        // Autopilot
+        sim.map_client_event_to_sim_event(EventID::AutopilotOff as u32, "AUTOPILOT_OFF")?;
 
         // Set maskable notification priorities
         sim.set_notification_group_priority(
@@ -89,31 +215,132 @@ impl Input {
             GroupID::Rudder as u32,
             SIMCONNECT_GROUP_PRIORITY_HIGHEST_MASKABLE,
         )?;
+        // Custom events are only sent to us, so they don't need to be masked
+        sim.set_notification_group_priority(
+            GroupID::Sidesticks as u32,
+            SIMCONNECT_GROUP_PRIORITY_HIGHEST,
+        )?;
+
+        Ok(())
+    }
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        let dt = ctx.sim_time.delta();
+
+        // Shape each axis before combining the sidesticks, so that noise around neutral on one
+        // sidestick can't trigger the dual input detection.
+        let (pitch, roll, yaw, keyboard_rate) = match &self.variables {
+            Some(variables) => (
+                variables.pitch.shaping(),
+                variables.roll.shaping(),
+                variables.yaw.shaping(),
+                variables.keyboard_rate.get_value::<f64>().max(0.0),
+            ),
+            None => (
+                AxisShaping::default(),
+                AxisShaping::default(),
+                AxisShaping::default(),
+                0.0,
+            ),
+        };
+
+        let captain = Sidestick {
+            x: roll.apply(self.captain_roll.update(keyboard_rate, dt)),
+            y: pitch.apply(self.captain_pitch.update(keyboard_rate, dt)),
+            takeover: self.captain_takeover,
+        };
+        let first_officer = Sidestick {
+            x: roll.apply(self.first_officer_roll.update(keyboard_rate, dt)),
+            y: pitch.apply(self.first_officer_pitch.update(keyboard_rate, dt)),
+            takeover: self.first_officer_takeover,
+        };
+        let (x, y) = self.sidestick_priority.update(&captain, &first_officer, dt);
+        self.yoke_x = x;
+        self.yoke_y = y;
+        self.rudder = yaw.apply(self.rudder_pedals.update(keyboard_rate, dt));
+
+        // Publish the priority and dual input annunciations for the cockpit
+        if let Some(variables) = &self.variables {
+            variables
+                .sidestick_priority
+                .set_value(match self.sidestick_priority.priority {
+                    Priority::None => 0.0,
+                    Priority::Captain => 1.0,
+                    Priority::FirstOfficer => 2.0,
+                });
+            variables
+                .dual_input
+                .set_value(self.sidestick_priority.dual_input as u8 as f64);
+        }
+
+        Ok(())
+    }
+
+    // Disconnects the autopilot, the event is also seen by the rest of the aircraft systems
+    pub(crate) fn disconnect_autopilot(&self, sim: &SimConnect) -> Result<()> {
+        sim.transmit_client_event(
+            SIMCONNECT_OBJECT_ID_USER,
+            EventID::AutopilotOff as u32,
+            0,
+            SIMCONNECT_GROUP_PRIORITY_HIGHEST,
+            SIMCONNECT_EVENT_FLAG_GROUPID_IS_PRIORITY,
+        )?;
 
         Ok(())
     }
 
-    pub(crate) fn update(&mut self, event: &SIMCONNECT_RECV_EVENT) -> Result<()> {
+    pub(crate) fn handle_event(&mut self, event: &SIMCONNECT_RECV_EVENT) -> Result<()> {
         // scale from [-16384, 16384] to [-1,1] and reverse the sign
         let map = |n| 0.0 - (n as f64 / 16384.0);
 
         match event.uEventID.into() {
-            EventID::ElevatorSet => {
-                self.yoke_y = map(event.dwData);
+            EventID::ElevatorSet | EventID::CaptainSidestickPitchSet => {
+                self.captain_pitch.set(map(event.dwData));
             }
-            EventID::AileronsSet => {
-                self.yoke_x = map(event.dwData);
+            EventID::ElevatorUp => {
+                self.captain_pitch.step(1.0);
+            }
+            EventID::ElevatorDown => {
+                self.captain_pitch.step(-1.0);
+            }
+            EventID::AileronsSet | EventID::CaptainSidestickRollSet => {
+                self.captain_roll.set(map(event.dwData));
             }
             EventID::CenterAileronsRudder => {
-                self.yoke_x = 0.0;
-                self.rudder = 0.0;
+                self.captain_roll.center();
+                self.rudder_pedals.center();
+            }
+            EventID::AileronsLeft => {
+                self.captain_roll.step(-1.0);
+            }
+            EventID::AileronsRight => {
+                self.captain_roll.step(1.0);
             }
             EventID::RudderSet => {
-                self.rudder = map(event.dwData);
+                self.rudder_pedals.set(map(event.dwData));
             }
             EventID::RudderCenter => {
-                self.rudder = 0.0;
+                self.rudder_pedals.center();
+            }
+            EventID::RudderLeft => {
+                self.rudder_pedals.step(-1.0);
+            }
+            EventID::RudderRight => {
+                self.rudder_pedals.step(1.0);
+            }
+            EventID::CaptainTakeoverSet => {
+                self.captain_takeover = event.dwData != 0;
+            }
+            EventID::FirstOfficerSidestickPitchSet => {
+                self.first_officer_pitch.set(map(event.dwData));
+            }
+            EventID::FirstOfficerSidestickRollSet => {
+                self.first_officer_roll.set(map(event.dwData));
+            }
+            EventID::FirstOfficerTakeoverSet => {
+                self.first_officer_takeover = event.dwData != 0;
             }
+            EventID::AutopilotOff => {}
         }
 
         Ok(())
@@ -0,0 +1,96 @@
+use crate::{
+    fbw::FBW,
+    hydraulics::{HydraulicSystem, Hydraulics},
+    Result,
+};
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum ControlLaw {
+    Normal,
+    Alternate,
+    Direct,
+}
+
+#[derive(Clone)]
+pub(crate) struct ControlLaws {
+    pub(crate) active: ControlLaw,
+}
+impl Default for ControlLaws {
+    fn default() -> Self {
+        ControlLaws {
+            active: ControlLaw::Normal,
+        }
+    }
+}
+
+impl ControlLaws {
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        self.active = Self::hydraulic_reconfiguration(&ctx.hydraulics);
+
+        // Losing two ADRs (or being unable to tell which one is wrong) reverts to alternate law
+        if self.active == ControlLaw::Normal && ctx.adr.degraded() {
+            self.active = ControlLaw::Alternate;
+        }
+
+        // The normal law can't be flown without its feedback sensors
+        let sensors_valid =
+            ctx.data.alpha_valid() && ctx.data.gforce_valid() && ctx.data.pitch_rate_valid();
+        if self.active == ControlLaw::Normal && !sensors_valid {
+            self.active = ControlLaw::Alternate;
+        }
+
+        Ok(())
+    }
+
+    // Law reconfiguration following hydraulic failures, from the FCOM in 1.27.30 under "Reconfiguration Control Laws"
+    // - Green + Blue: alternate law
+    // - Green + Yellow: alternate law (the THS is lost)
+    // - Blue + Yellow: normal law is kept
+    // - Triple failure: direct law, as no actuator is left to fly the laws with
+    fn hydraulic_reconfiguration(hydraulics: &Hydraulics) -> ControlLaw {
+        let green = hydraulics.is_pressurised(HydraulicSystem::Green);
+        let blue = hydraulics.is_pressurised(HydraulicSystem::Blue);
+
+        if !green && !blue && !hydraulics.is_pressurised(HydraulicSystem::Yellow) {
+            ControlLaw::Direct
+        } else if (!green && !blue) || !hydraulics.ths_available() {
+            ControlLaw::Alternate
+        } else {
+            ControlLaw::Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hydraulic_reconfiguration() {
+        // (Green, Blue, Yellow) pressurised -> law
+        let cases = [
+            ((true, true, true), ControlLaw::Normal),
+            // Single failures
+            ((false, true, true), ControlLaw::Normal),
+            ((true, false, true), ControlLaw::Normal),
+            ((true, true, false), ControlLaw::Normal),
+            // Dual failures
+            ((false, false, true), ControlLaw::Alternate),
+            ((false, true, false), ControlLaw::Alternate),
+            ((true, false, false), ControlLaw::Normal),
+            // Triple failure
+            ((false, false, false), ControlLaw::Direct),
+        ];
+        for &((green, blue, yellow), law) in cases.iter() {
+            let hydraulics = Hydraulics::pressurised(green, blue, yellow);
+            assert_eq!(
+                ControlLaws::hydraulic_reconfiguration(&hydraulics),
+                law,
+                "green: {}, blue: {}, yellow: {}",
+                green,
+                blue,
+                yellow
+            );
+        }
+    }
+}
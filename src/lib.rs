@@ -3,14 +3,33 @@ use ::msfs::{
     sim_connect::SimConnectRecv,
 };
 
+mod actuator;
+mod adr;
+mod allocation;
+mod atmosphere;
+mod autopilot;
 mod controls;
 mod data;
 mod fbw;
+mod flight_model;
+mod flight_phase;
+mod gain_schedule;
+mod history;
+mod hydraulics;
 mod input;
+mod law;
+mod limits;
 mod pid;
+mod pitch_compensation;
 mod pitch_control;
 mod protections;
+mod shaping;
+mod sidestick;
+mod signal;
 mod sim_time;
+mod surfaces;
+mod units;
+mod weight_balance;
 
 pub(crate) type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -47,6 +66,18 @@ pub(crate) fn linear_decay_coefficient(position: f64, start: f64, end: f64) -> f
     }
 }
 
+// Asserts that a value is within the tolerance of the expected one
+#[cfg(test)]
+pub(crate) fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
 #[gauge(name = FBW)]
 async fn fbw_impl(mut gauge: Gauge) -> Result<()> {
     let sim = gauge.open_simconnect("A32NX_FBW")?;
@@ -63,11 +94,15 @@ async fn fbw_impl(mut gauge: Gauge) -> Result<()> {
                 }
                 _ => {}
             },
-            MSFSEvent::SimConnect(recv) => {
-                if let SimConnectRecv::Event(event) = recv {
-                    fbw.input.update(event)?;
+            MSFSEvent::SimConnect(recv) => match recv {
+                SimConnectRecv::Event(event) => {
+                    fbw.input.handle_event(event)?;
+                }
+                SimConnectRecv::SimObjectData(data) => {
+                    fbw.data.receive(data, &fbw.sim);
                 }
-            }
+                _ => {}
+            },
         }
     }
 
@@ -0,0 +1,433 @@
+use crate::{
+    atmosphere,
+    fbw::FBW,
+    units::{Length, Pressure, Velocity},
+    Result,
+};
+
+// The aircraft variant, selected through the A32NX_FBW_AIRCRAFT_VARIANT L:var
+// (0 = A320, 1 = A319, 2 = A321)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum AircraftVariant {
+    A319,
+    A320,
+    A321,
+}
+impl From<f64> for AircraftVariant {
+    fn from(v: f64) -> Self {
+        match v as u8 {
+            1 => AircraftVariant::A319,
+            2 => AircraftVariant::A321,
+            _ => AircraftVariant::A320,
+        }
+    }
+}
+
+// The slats/flaps configurations. CONF 1 and CONF 1+F share the same handle position, and only
+// differ by the flaps being extended.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum Configuration {
+    Clean,
+    Conf1,
+    Conf1F,
+    Conf2,
+    Conf3,
+    Full,
+}
+
+impl Configuration {
+    // Beyond the FULL position, the handle is taken as FULL
+    pub(crate) fn from_handle(handle: u8, flaps_extended: bool) -> Configuration {
+        match handle {
+            0 => Configuration::Clean,
+            1 if flaps_extended => Configuration::Conf1F,
+            1 => Configuration::Conf1,
+            2 => Configuration::Conf2,
+            3 => Configuration::Conf3,
+            _ => Configuration::Full,
+        }
+    }
+}
+
+// The speed limitations of a variant, from the FCOM in 3.01.20 under "Maximum Speeds"
+struct VariantLimits {
+    vmo: f64,            // Maximum operating speed in knots
+    mmo: f64,            // Maximum operating mach
+    vfe: [f64; 5],       // Maximum speed in CONF 1, 1+F, 2, 3 and FULL, in knots
+    vle: f64,            // Maximum speed with the landing gear extended, in knots
+    mle: f64,            // Maximum mach with the landing gear extended
+    vlo_extension: f64,  // Maximum speed to extend the landing gear, in knots
+    vlo_retraction: f64, // Maximum speed to retract the landing gear, in knots
+    mlo: f64,            // Maximum mach to operate the landing gear
+}
+
+const A319_LIMITS: VariantLimits = VariantLimits {
+    vmo: 350.0,
+    mmo: 0.82,
+    vfe: [230.0, 215.0, 200.0, 185.0, 177.0],
+    vle: 280.0,
+    mle: 0.67,
+    vlo_extension: 250.0,
+    vlo_retraction: 220.0,
+    mlo: 0.60,
+};
+
+const A320_LIMITS: VariantLimits = VariantLimits {
+    vmo: 350.0,
+    mmo: 0.82,
+    vfe: [230.0, 215.0, 200.0, 185.0, 177.0],
+    vle: 280.0,
+    mle: 0.67,
+    vlo_extension: 250.0,
+    vlo_retraction: 220.0,
+    mlo: 0.60,
+};
+
+const A321_LIMITS: VariantLimits = VariantLimits {
+    vmo: 350.0,
+    mmo: 0.82,
+    vfe: [235.0, 225.0, 215.0, 195.0, 190.0],
+    vle: 280.0,
+    mle: 0.67,
+    vlo_extension: 250.0,
+    vlo_retraction: 220.0,
+    mlo: 0.60,
+};
+
+impl AircraftVariant {
+    fn limits(self) -> &'static VariantLimits {
+        match self {
+            AircraftVariant::A319 => &A319_LIMITS,
+            AircraftVariant::A320 => &A320_LIMITS,
+            AircraftVariant::A321 => &A321_LIMITS,
+        }
+    }
+}
+
+// The flight parameters the limits are computed from, for one frame
+#[derive(Default, Clone, Copy)]
+struct SpeedLimitInputs {
+    flaps: u8, // The flaps handle position
+    flaps_extended: bool,
+    gear_handle_down: bool,
+    gear_extension: f64,
+    gear_in_transit: bool,
+    static_pressure: Pressure,
+    ias: Velocity,
+    mach: f64,
+}
+
+impl SpeedLimitInputs {
+    fn from_fbw(ctx: &FBW) -> SpeedLimitInputs {
+        SpeedLimitInputs {
+            flaps: ctx.data.flaps(),
+            flaps_extended: ctx.data.flaps_extended(),
+            gear_handle_down: ctx.data.gear_handle_down(),
+            gear_extension: ctx.data.gear_extension(),
+            gear_in_transit: ctx.data.gear_in_transit(),
+            static_pressure: ctx.data.static_pressure(),
+            ias: ctx.adr.ias(),
+            mach: ctx.data.mach(),
+        }
+    }
+}
+
+// The speed limits applicable to the current configuration.
+// The most restrictive one is published for the displays through A32NX_FBW_MAX_SPEED (knots),
+// and A32NX_FBW_OVERSPEED_WARNING is set when it is exceeded.
+#[derive(Clone)]
+pub(crate) struct SpeedLimits {
+    pub(crate) variant: AircraftVariant,
+    pub(crate) configuration: Configuration,
+    pub(crate) vmo: Velocity,
+    pub(crate) mmo: f64,
+    pub(crate) vfe: Option<Velocity>, // None in the clean configuration
+    pub(crate) vle: Velocity,
+    pub(crate) vlo: Velocity, // In the current gear operation direction
+    pub(crate) crossover_altitude: Length, // The pressure altitude above which MMO is limiting
+    pub(crate) max_speed: Velocity, // The most restrictive limit, as a calibrated airspeed
+    pub(crate) overspeed: bool,
+}
+impl Default for SpeedLimits {
+    fn default() -> Self {
+        SpeedLimits {
+            variant: AircraftVariant::A320,
+            configuration: Configuration::Clean,
+            vmo: Velocity::from_knots(A320_LIMITS.vmo),
+            mmo: A320_LIMITS.mmo,
+            vfe: None,
+            vle: Velocity::from_knots(A320_LIMITS.vle),
+            vlo: Velocity::from_knots(A320_LIMITS.vlo_extension),
+            crossover_altitude: Length::default(),
+            max_speed: Velocity::from_knots(A320_LIMITS.vmo),
+            overspeed: false,
+        }
+    }
+}
+
+impl SpeedLimits {
+    // The overspeed warning is triggered slightly above the limits, from the FCOM in 1.31.40
+    // under "Overspeed"
+    const OVERSPEED_MARGIN: Velocity = Velocity::from_knots(4.0);
+    const OVERSPEED_MACH_MARGIN: f64 = 0.006;
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        use msfs::msfs::legacy::NamedVariable;
+
+        self.calculate(
+            AircraftVariant::from(
+                NamedVariable::from("A32NX_FBW_AIRCRAFT_VARIANT").get_value::<f64>(),
+            ),
+            &SpeedLimitInputs::from_fbw(ctx),
+        );
+
+        NamedVariable::from("A32NX_FBW_MAX_SPEED").set_value(self.max_speed.knots());
+        NamedVariable::from("A32NX_FBW_OVERSPEED_WARNING").set_value(self.overspeed as u8 as f64);
+
+        Ok(())
+    }
+
+    fn calculate(&mut self, variant: AircraftVariant, inputs: &SpeedLimitInputs) {
+        self.variant = variant;
+        let limits = variant.limits();
+        // A mach limit, as the calibrated airspeed it is reached at
+        let mach_limit = |mach| atmosphere::cas_from_mach(mach, inputs.static_pressure);
+
+        self.configuration = Configuration::from_handle(inputs.flaps, inputs.flaps_extended);
+        self.vmo = Velocity::from_knots(limits.vmo);
+        self.mmo = limits.mmo;
+        self.vfe = match self.configuration {
+            Configuration::Clean => None,
+            Configuration::Conf1 => Some(limits.vfe[0]),
+            Configuration::Conf1F => Some(limits.vfe[1]),
+            Configuration::Conf2 => Some(limits.vfe[2]),
+            Configuration::Conf3 => Some(limits.vfe[3]),
+            Configuration::Full => Some(limits.vfe[4]),
+        }
+        .map(Velocity::from_knots);
+        self.vle = Velocity::from_knots(limits.vle).min(mach_limit(limits.mle));
+        self.vlo = if inputs.gear_handle_down {
+            Velocity::from_knots(limits.vlo_extension)
+        } else {
+            Velocity::from_knots(limits.vlo_retraction)
+        }
+        .min(mach_limit(limits.mlo));
+        self.crossover_altitude = atmosphere::crossover_altitude(self.vmo, self.mmo);
+
+        let mut max_speed = self.vmo.min(mach_limit(self.mmo));
+        if let Some(vfe) = self.vfe {
+            max_speed = max_speed.min(vfe);
+        }
+        if inputs.gear_extension > 0.0 {
+            max_speed = max_speed.min(self.vle);
+        }
+        if inputs.gear_in_transit {
+            max_speed = max_speed.min(self.vlo);
+        }
+        self.max_speed = max_speed;
+
+        self.overspeed = inputs.ias > self.max_speed + Self::OVERSPEED_MARGIN
+            || inputs.mach > self.mmo + Self::OVERSPEED_MACH_MARGIN;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    fn sea_level() -> SpeedLimitInputs {
+        SpeedLimitInputs {
+            static_pressure: atmosphere::standard_pressure(Length::default()),
+            ias: Velocity::from_knots(250.0),
+            mach: 0.38,
+            ..SpeedLimitInputs::default()
+        }
+    }
+
+    fn limits(variant: AircraftVariant, inputs: SpeedLimitInputs) -> SpeedLimits {
+        let mut limits = SpeedLimits::default();
+        limits.calculate(variant, &inputs);
+        limits
+    }
+
+    // The VFE of a variant in each configuration, in knots
+    fn vfe(variant: AircraftVariant) -> Vec<Option<f64>> {
+        [
+            (0, false),
+            (1, false),
+            (1, true),
+            (2, true),
+            (3, true),
+            (4, true),
+        ]
+        .iter()
+        .map(|&(flaps, flaps_extended)| {
+            limits(
+                variant,
+                SpeedLimitInputs {
+                    flaps,
+                    flaps_extended,
+                    ..sea_level()
+                },
+            )
+            .vfe
+            .map(Velocity::knots)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn variants() {
+        assert_eq!(AircraftVariant::from(0.0), AircraftVariant::A320);
+        assert_eq!(AircraftVariant::from(1.0), AircraftVariant::A319);
+        assert_eq!(AircraftVariant::from(2.0), AircraftVariant::A321);
+        assert_eq!(AircraftVariant::from(7.0), AircraftVariant::A320);
+
+        let a320 = [
+            None,
+            Some(230.0),
+            Some(215.0),
+            Some(200.0),
+            Some(185.0),
+            Some(177.0),
+        ];
+        assert_eq!(vfe(AircraftVariant::A319), a320);
+        assert_eq!(vfe(AircraftVariant::A320), a320);
+        assert_eq!(
+            vfe(AircraftVariant::A321),
+            [
+                None,
+                Some(235.0),
+                Some(225.0),
+                Some(215.0),
+                Some(195.0),
+                Some(190.0)
+            ]
+        );
+
+        for &variant in [
+            AircraftVariant::A319,
+            AircraftVariant::A320,
+            AircraftVariant::A321,
+        ]
+        .iter()
+        {
+            let limits = limits(variant, sea_level());
+            assert_close(limits.vmo.knots(), 350.0, 1e-12);
+            assert_close(limits.mmo, 0.82, 1e-12);
+            assert_close(limits.vle.knots(), 280.0, 1e-12);
+        }
+    }
+
+    #[test]
+    fn configurations() {
+        let configuration = Configuration::from_handle;
+
+        assert_eq!(configuration(0, false), Configuration::Clean);
+        assert_eq!(configuration(0, true), Configuration::Clean);
+        assert_eq!(configuration(1, false), Configuration::Conf1);
+        assert_eq!(configuration(1, true), Configuration::Conf1F);
+        assert_eq!(configuration(2, true), Configuration::Conf2);
+        assert_eq!(configuration(3, true), Configuration::Conf3);
+        assert_eq!(configuration(4, true), Configuration::Full);
+        assert_eq!(configuration(5, true), Configuration::Full);
+        assert_eq!(configuration(u8::MAX, true), Configuration::Full);
+    }
+
+    #[test]
+    fn crossover_altitude() {
+        let limits = limits(AircraftVariant::A320, sea_level());
+
+        assert_close(limits.crossover_altitude.feet(), 24_554.0, 1.0);
+    }
+
+    #[test]
+    fn most_restrictive_limit() {
+        let max_speed = |inputs| limits(AircraftVariant::A320, inputs).max_speed.knots();
+
+        assert_close(max_speed(sea_level()), 350.0, 1e-9);
+        // MMO above the crossover altitude
+        let fl350 = atmosphere::standard_pressure(Length::from_feet(35_000.0));
+        assert_close(
+            max_speed(SpeedLimitInputs {
+                static_pressure: fl350,
+                ..sea_level()
+            }),
+            atmosphere::cas_from_mach(0.82, fl350).knots(),
+            1e-9,
+        );
+        // VFE, then VLE with the gear down and VLO while it moves
+        assert_close(
+            max_speed(SpeedLimitInputs {
+                flaps: 4,
+                flaps_extended: true,
+                ..sea_level()
+            }),
+            177.0,
+            1e-9,
+        );
+        let gear_down = SpeedLimitInputs {
+            gear_handle_down: true,
+            gear_extension: 1.0,
+            ..sea_level()
+        };
+        assert_close(max_speed(gear_down), 280.0, 1e-9);
+        assert_close(
+            max_speed(SpeedLimitInputs {
+                gear_in_transit: true,
+                gear_extension: 0.5,
+                ..gear_down
+            }),
+            250.0,
+            1e-9,
+        );
+        assert_close(
+            max_speed(SpeedLimitInputs {
+                gear_handle_down: false,
+                gear_in_transit: true,
+                gear_extension: 0.5,
+                ..sea_level()
+            }),
+            220.0,
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn overspeed_warning() {
+        let overspeed = |ias, mach| {
+            limits(
+                AircraftVariant::A320,
+                SpeedLimitInputs {
+                    ias: Velocity::from_knots(ias),
+                    mach,
+                    ..sea_level()
+                },
+            )
+            .overspeed
+        };
+
+        // Triggered 4 knots or M0.006 above the limits
+        assert!(!overspeed(350.0, 0.53));
+        assert!(!overspeed(353.9, 0.53));
+        assert!(overspeed(354.1, 0.53));
+        assert!(!overspeed(300.0, 0.825));
+        assert!(overspeed(300.0, 0.827));
+
+        let full = |ias| {
+            limits(
+                AircraftVariant::A320,
+                SpeedLimitInputs {
+                    flaps: 4,
+                    flaps_extended: true,
+                    ias: Velocity::from_knots(ias),
+                    ..sea_level()
+                },
+            )
+            .overspeed
+        };
+        assert!(!full(180.0));
+        assert!(full(182.0));
+    }
+}
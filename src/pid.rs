@@ -7,8 +7,12 @@ pub(crate) struct PIDController {
     kp: f64,
     kd: f64,
     ki: f64,
-    integral: f64,
-    last_error: f64,
+    gain_scale: f64,                // Factor applied to all gains, see set_gain_scale
+    derivative_time_constant: f64,  // Time constant of the derivative low pass filter (seconds)
+    output_rate_limit: Option<f64>, // Maximum output change (per second)
+    integral: f64, // Integral term, kept in output units so gain changes don't bump the output
+    derivative: f64, // Filtered rate of change of the measurement
+    last_measurement: Option<f64>,
     last_output: f64,
 }
 
@@ -26,50 +30,225 @@ impl PIDController {
             kp,
             kd,
             ki,
+            gain_scale: 1.0,
+            derivative_time_constant: 0.0,
+            output_rate_limit: None,
             integral: 0.0,
-            last_error: 0.0,
+            derivative: 0.0,
+            last_measurement: None,
             last_output: 0.0,
         }
     }
 
-    pub(crate) fn update(&mut self, error: f64, dt: f64) -> f64 {
-        // Proportional term
-        let p = self.kp * error;
+    // Low pass filters the derivative term, so it doesn't amplify the measurement noise
+    pub(crate) fn with_derivative_filter(mut self, time_constant: f64) -> PIDController {
+        self.derivative_time_constant = time_constant;
+        self
+    }
 
-        // Integral term
-        self.integral += error * dt;
-        let i = self.ki * self.integral;
+    // Limits how fast the output may change
+    pub(crate) fn with_output_rate_limit(mut self, rate: f64) -> PIDController {
+        self.output_rate_limit = Some(rate);
+        self
+    }
+
+    // Scales the gains, e.g. from a gain schedule. The integral term is kept in output units, so
+    // this can be changed at every update without disturbing the output.
+    pub(crate) fn set_gain_scale(&mut self, scale: f64) {
+        self.gain_scale = scale;
+    }
+
+    pub(crate) fn update(&mut self, setpoint: f64, measurement: f64, dt: f64) -> f64 {
+        // Nothing can be integrated or differentiated without time passing
+        if dt <= 0.0 || !dt.is_finite() {
+            return self.last_output;
+        }
+
+        let error = setpoint - measurement;
+
+        // Proportional term
+        let p = self.kp * self.gain_scale * error;
 
         // Derivative term
-        let d = self.kd * ((error - self.last_error) / dt);
+        // It is taken on the measurement rather than the error, so that setpoint steps
+        // don't kick the output.
+        if let Some(last_measurement) = self.last_measurement {
+            let rate = -(measurement - last_measurement) / dt;
+            self.derivative += dt / (self.derivative_time_constant + dt) * (rate - self.derivative);
+        }
+        self.last_measurement = Some(measurement);
+        let d = self.kd * self.gain_scale * self.derivative;
+
+        // Integral term
+        // Conditional integration guards against windup: the integral is frozen while the output
+        // is saturated and the error would push it further into saturation.
+        let integral = self.integral + self.ki * self.gain_scale * error * dt;
+        let unsaturated = p + integral + d;
+        let winding_up = (unsaturated > self.output_max && error > 0.0)
+            || (unsaturated < self.output_min && error < 0.0);
+        if !winding_up {
+            self.integral = integral;
+        }
 
-        let output = clamp(p + i + d, self.output_min, self.output_max);
+        let mut output = clamp(p + self.integral + d, self.output_min, self.output_max);
+        if let Some(rate) = self.output_rate_limit {
+            let change = rate * dt;
+            output = output
+                .max(self.last_output - change)
+                .min(self.last_output + change);
+        }
 
         // Save terms
         self.last_output = output;
-        self.last_error = error;
 
         output
     }
 
-    // Guard against integrator windup
-    pub(crate) fn update_anti_windup(&mut self, error: f64, dt: f64) -> f64 {
-        if (self.last_output >= self.output_min && self.last_output <= self.output_max)
-            || error.signum() != self.last_output.signum()
-        {
-            self.integral -= error * dt;
+    // Bumpless transfer: initialises the controller so that it continues from the given output,
+    // e.g. when taking over from another controller or from direct control.
+    pub(crate) fn initialise(&mut self, output: f64, setpoint: f64, measurement: f64) {
+        let output = clamp(output, self.output_min, self.output_max);
+        self.integral = output - self.kp * self.gain_scale * (setpoint - measurement);
+        self.derivative = 0.0;
+        self.last_measurement = Some(measurement);
+        self.last_output = output;
+    }
+
+    // Clears the integral, the derivative and the last measurement, e.g. when a law takes over
+    // a controller for a new purpose
+    pub(crate) fn reset(&mut self) {
+        self.integral = 0.0;
+        self.derivative = 0.0;
+        self.last_measurement = None;
+        self.last_output = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    const DT: f64 = 0.02;
+
+    #[test]
+    fn proportional_step() {
+        let mut pid = PIDController::new(-10.0, 10.0, 2.0, 0.0, 0.0);
+
+        assert_close(pid.update(1.5, 0.0, DT), 3.0, 1e-12);
+        assert_close(pid.update(-1.0, 0.5, DT), -3.0, 1e-12);
+    }
+
+    #[test]
+    fn integral_step() {
+        let mut pid = PIDController::new(-10.0, 10.0, 0.0, 0.5, 0.0);
+
+        let mut output = 0.0;
+        for _ in 0..(2.0 / DT) as usize {
+            output = pid.update(1.0, 0.0, DT);
+        }
+        // ki * error * time
+        assert_close(output, 1.0, 1e-9);
+    }
+
+    #[test]
+    fn closed_loop_step_has_no_steady_state_error() {
+        // A first order plant, with a 0.5 seconds time constant
+        let mut pid = PIDController::new(-10.0, 10.0, 1.0, 2.0, 0.0);
+        let mut measurement = 0.0;
+        for _ in 0..(20.0 / DT) as usize {
+            let output = pid.update(1.0, measurement, DT);
+            measurement += (output - measurement) * DT / 0.5;
+        }
+
+        assert_close(measurement, 1.0, 1e-3);
+    }
+
+    #[test]
+    fn setpoint_step_does_not_kick_the_derivative() {
+        let mut pid = PIDController::new(-10.0, 10.0, 0.0, 0.0, 1.0);
+        pid.update(0.0, 0.0, DT);
+
+        assert_close(pid.update(5.0, 0.0, DT), 0.0, 1e-12);
+    }
+
+    #[test]
+    fn measurement_ramp_derivative() {
+        // The filtered derivative settles on the ramp rate
+        let mut pid = PIDController::new(-10.0, 10.0, 0.0, 0.0, 2.0).with_derivative_filter(0.1);
+        let rate = 0.5;
+        let mut output = 0.0;
+        let mut first = None;
+        for step in 0..(2.0 / DT) as usize {
+            output = pid.update(0.0, rate * step as f64 * DT, DT);
+            if step == 1 {
+                first = Some(output);
+            }
+        }
+
+        // The filter delays the response to the ramp
+        assert!(first.unwrap().abs() < 0.5 * 2.0 * rate);
+        assert_close(output, -2.0 * rate, 1e-6);
+    }
+
+    #[test]
+    fn saturation_does_not_wind_up() {
+        let mut pid = PIDController::new(-1.0, 1.0, 0.5, 1.0, 0.0);
+
+        // Saturated for a long time
+        for _ in 0..(30.0 / DT) as usize {
+            assert!(pid.update(10.0, 0.0, DT) <= 1.0);
+        }
+        assert!(pid.integral <= 1.0);
+
+        // The output leaves the saturation as soon as the error reverses
+        let output = pid.update(-0.5, 0.0, DT);
+        assert!(output < 1.0);
+    }
+
+    #[test]
+    fn saturation_limits() {
+        let mut pid = PIDController::new(-2.0, 1.0, 10.0, 0.0, 0.0);
+
+        assert_close(pid.update(1.0, 0.0, DT), 1.0, 1e-12);
+        assert_close(pid.update(-1.0, 0.0, DT), -2.0, 1e-12);
+    }
+
+    #[test]
+    fn output_rate_limit() {
+        let mut pid = PIDController::new(-10.0, 10.0, 1.0, 0.0, 0.0).with_output_rate_limit(2.0);
+
+        assert_close(pid.update(5.0, 0.0, DT), 2.0 * DT, 1e-12);
+        assert_close(pid.update(5.0, 0.0, DT), 4.0 * DT, 1e-12);
+    }
+
+    #[test]
+    fn no_time_keeps_the_output() {
+        let mut pid = PIDController::new(-10.0, 10.0, 1.0, 1.0, 1.0);
+        let output = pid.update(1.0, 0.0, DT);
+
+        assert_close(pid.update(3.0, 0.0, 0.0), output, 1e-12);
+        assert_close(pid.update(3.0, 0.0, f64::NAN), output, 1e-12);
+    }
+
+    #[test]
+    fn bumpless_transfer() {
+        let mut pid = PIDController::new(-1.0, 1.0, 0.5, 0.2, 0.1);
+        pid.initialise(0.4, 2.0, 1.5);
+
+        assert_close(pid.update(2.0, 1.5, DT), 0.4 + 0.2 * 0.5 * DT, 1e-12);
+    }
+
+    #[test]
+    fn reset() {
+        let mut pid = PIDController::new(-10.0, 10.0, 1.0, 1.0, 1.0);
+        for _ in 0..100 {
+            pid.update(1.0, 0.0, DT);
         }
-        self.update(error, dt)
-    }
-
-    pub(crate) fn query(&mut self, error: f64, dt: f64) -> f64 {
-        let last_error = self.last_error;
-        let last_output = self.last_output;
-        let integral = self.integral;
-        let update = self.update(error, dt);
-        self.last_error = last_error;
-        self.last_output = last_output;
-        self.integral = integral;
-        update
+        pid.update(1.0, 0.5, DT);
+        pid.reset();
+
+        // Only the proportional term: no integral and no derivative of the measurement jump
+        assert_close(pid.update(1.0, 0.0, DT), 1.0 + DT, 1e-12);
     }
 }
@@ -0,0 +1,116 @@
+// Feed-forward pitch compensation of the configuration changes.
+//
+// Extending the flaps, the speedbrakes or the landing gear changes the pitch moment, which the
+// load factor law only corrects once the aircraft has started to pitch. The elevator deflection
+// which cancels these moments is computed from the flight model coefficients, so that the law can
+// apply it as the surfaces move.
+
+use crate::{clamp, flight_model, units::Angle, Result};
+
+#[derive(Clone, Copy)]
+pub(crate) struct PitchCompensation {
+    flaps_moment: f64,    // Pitch moment coefficient with the flaps fully extended
+    gear_moment: f64,     // Pitch moment coefficient with the gear down
+    spoilers_moment: f64, // Pitch moment coefficient with the spoilers fully extended
+    elevator_moment: f64, // Pitch moment coefficient per radian of elevator, trailing edge down
+    elevator_up_limit: Angle,
+    elevator_down_limit: Angle,
+    full_flaps_angle: Angle, // The trailing edge flaps deflection in CONF FULL
+}
+
+impl PitchCompensation {
+    pub(crate) fn load() -> Result<PitchCompensation> {
+        let aerodynamics = |key| flight_model::value("AERODYNAMICS", key);
+        let geometry = |key| flight_model::value("AIRPLANE_GEOMETRY", key);
+
+        Ok(PitchCompensation {
+            flaps_moment: aerodynamics("pitch_moment_flaps")?,
+            gear_moment: aerodynamics("pitch_moment_gear")?,
+            spoilers_moment: aerodynamics("pitch_moment_spoilers")?,
+            elevator_moment: aerodynamics("pitch_moment_delta_elevator")?,
+            elevator_up_limit: Angle::from_degrees(geometry("elevator_up_limit")?),
+            elevator_down_limit: Angle::from_degrees(geometry("elevator_down_limit")?),
+            // The first value of a flaps position is its deflection, and CONF FULL is the last
+            full_flaps_angle: Angle::from_degrees(
+                flight_model::values("FLAPS.0", "flaps-position.4")?[0],
+            ),
+        })
+    }
+
+    // The elevator position (-1.0 full down, +1.0 full up) which cancels the pitch moment of the
+    // flaps deflection, the gear extension (0.0 up, 1.0 down) and the spoilers extension (0.0
+    // retracted, 1.0 fully extended)
+    pub(crate) fn elevator(&self, flaps_angle: Angle, gear: f64, spoilers: f64) -> f64 {
+        if self.elevator_moment == 0.0 {
+            return 0.0;
+        }
+
+        let flaps = clamp(flaps_angle / self.full_flaps_angle, 0.0, 1.0);
+        let moment =
+            flaps * self.flaps_moment + gear * self.gear_moment + spoilers * self.spoilers_moment;
+
+        // The flight model deflects the elevator trailing edge down, so a nose down moment is
+        // cancelled by raising it
+        let deflection = Angle::from_radians(moment / self.elevator_moment);
+        if deflection >= Angle::default() {
+            (deflection.degrees() / self.elevator_up_limit.degrees()).min(1.0)
+        } else {
+            (deflection.degrees() / self.elevator_down_limit.degrees()).max(-1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    fn compensation() -> PitchCompensation {
+        PitchCompensation::load().unwrap()
+    }
+
+    #[test]
+    fn clean_configuration() {
+        assert_close(
+            compensation().elevator(Angle::default(), 0.0, 0.0),
+            0.0,
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn flaps() {
+        let compensation = compensation();
+        let flaps = |degrees| compensation.elevator(Angle::from_degrees(degrees), 0.0, 0.0);
+
+        // The flaps pitch down, -0.6355 / -6.51784 radians of elevator up over its 16 degrees
+        assert_close(flaps(35.0), 0.3492, 1e-4);
+        assert_close(flaps(17.5), flaps(35.0) / 2.0, 1e-12);
+        assert_close(flaps(40.0), flaps(35.0), 1e-12);
+    }
+
+    #[test]
+    fn gear_and_spoilers() {
+        let compensation = compensation();
+
+        // Both pitch up a little, cancelled by lowering the elevator over its 11.5 degrees
+        assert_close(
+            compensation.elevator(Angle::default(), 1.0, 0.0),
+            -0.00185,
+            1e-5,
+        );
+        assert_close(
+            compensation.elevator(Angle::default(), 0.0, 1.0),
+            -0.00786,
+            1e-5,
+        );
+        // The moments add up
+        let full_flaps = Angle::from_degrees(35.0);
+        assert_close(
+            compensation.elevator(full_flaps, 1.0, 1.0),
+            compensation.elevator(full_flaps, 0.0, 0.0)
+                - (0.00242 + 0.01028) / 6.51784 / 16f64.to_radians(),
+            1e-12,
+        );
+    }
+}
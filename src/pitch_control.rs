@@ -1,48 +1,245 @@
-use crate::{fbw::FBW, Result};
+use crate::{
+    fbw::FBW,
+    flight_phase::FlightPhase,
+    signal::ConfirmTimer,
+    units::{Angle, AngularVelocity, Length},
+    Result,
+};
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub(crate) enum PitchControlMode {
     Ground,
     Flight,
     Flare,
+    Derotation, // From the main gear touchdown until the nose gear is on the ground
+}
+
+// What the mode is selected from in a frame
+#[derive(Clone, Copy)]
+pub(crate) struct PitchControlInputs {
+    pub(crate) dt: f64,
+    pub(crate) phase: FlightPhase,
+    pub(crate) on_ground: bool,
+    pub(crate) nose_gear_on_ground: bool,
+    pub(crate) pitch: Angle,
+    pub(crate) radio_height: Length,
+}
+
+impl PitchControlInputs {
+    fn from_fbw(ctx: &FBW) -> PitchControlInputs {
+        PitchControlInputs {
+            dt: ctx.sim_time.delta(),
+            phase: ctx.flight_phase.phase,
+            on_ground: ctx.data.on_ground(),
+            nose_gear_on_ground: ctx.data.nose_gear_on_ground(),
+            pitch: ctx.data.pitch(),
+            radio_height: ctx.data.radio_height(),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub(crate) struct PitchControl {
     pub(crate) mode: PitchControlMode,
-    pub(crate) flare_effect: f64,
+    pub(crate) flare_effect: f64, // Blends from flight mode (0.0) to flare mode (1.0)
+    pub(crate) memorised_attitude: Angle, // The pitch attitude when entering flare mode
+    pub(crate) flare_attitude: Angle, // The pitch attitude the flare mode flies
+    derotation_timer: ConfirmTimer,
 }
 impl Default for PitchControl {
     fn default() -> Self {
         PitchControl {
             mode: PitchControlMode::Ground,
             flare_effect: 0.0,
+            memorised_attitude: Angle::default(),
+            flare_attitude: Angle::default(),
+            derotation_timer: ConfirmTimer::new(Self::MAX_DEROTATION_TIME),
         }
     }
 }
 
 impl PitchControl {
-    pub(crate) fn update(&mut self, _: &FBW) -> Result<()> {
-        match self.mode {
-            PitchControlMode::Ground => self.handle_ground_transitions(),
-            PitchControlMode::Flight => self.handle_flight_transitions(),
-            PitchControlMode::Flare => self.handle_flare_transitions(),
-        }
+    // Time to blend between the flight and flare modes
+    const FLARE_TRANSITION_TIME: f64 = 1.0;
+    // In flare mode the memorised attitude is progressively reduced to 2 degrees nose down over
+    // 8 seconds, from 30 feet, from the FCOM in 1.27.20 under "Flare Mode"
+    const FLARE_PITCH_DOWN_HEIGHT: Length = Length::from_feet(30.0);
+    const FLARE_PITCH_DOWN_ATTITUDE: Angle = Angle::from_degrees(-2.0);
+    const FLARE_PITCH_DOWN_TIME: f64 = 8.0;
+    // Ground mode is engaged once the nose gear is on the ground, or at the latest after this time
+    const MAX_DEROTATION_TIME: f64 = 5.0;
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        self.process(&PitchControlInputs::from_fbw(ctx));
 
         Ok(())
     }
 
-    fn handle_ground_transitions(&mut self) {
-        /*
-        if radio_altimeter > 50 || (in_flight && pitch_attitude > 8.0) {
+    // Selects the mode of a frame, and updates the flare attitude
+    pub(crate) fn process(&mut self, inputs: &PitchControlInputs) {
+        let mode = self.select_mode(
+            inputs.phase,
+            inputs.on_ground,
+            inputs.nose_gear_on_ground,
+            inputs.dt,
+        );
+        match mode {
+            PitchControlMode::Ground => self.handle_ground(),
+            PitchControlMode::Flight => self.handle_flight(inputs.dt),
+            PitchControlMode::Flare => self.handle_flare(inputs),
+            PitchControlMode::Derotation => self.handle_derotation(),
+        }
+        self.mode = mode;
+    }
 
+    // The mode of this frame, from the mode of the previous frame
+    fn select_mode(
+        &mut self,
+        phase: FlightPhase,
+        on_ground: bool,
+        nose_gear_on_ground: bool,
+        dt: f64,
+    ) -> PitchControlMode {
+        if self.derotating(on_ground, nose_gear_on_ground, dt) {
+            PitchControlMode::Derotation
         } else {
+            match phase {
+                FlightPhase::Flare => PitchControlMode::Flare,
+                phase if phase.on_ground() => PitchControlMode::Ground,
+                _ => PitchControlMode::Flight,
+            }
+        }
+    }
+
+    // True from the main gear touchdown after a flare, until the nose gear is on the ground
+    fn derotating(&mut self, on_ground: bool, nose_gear_on_ground: bool, dt: f64) -> bool {
+        let derotating = matches!(
+            self.mode,
+            PitchControlMode::Flare | PitchControlMode::Derotation
+        ) && on_ground
+            && !nose_gear_on_ground;
+        let timed_out = self.derotation_timer.update(derotating, dt);
+        derotating && !timed_out
+    }
+
+    fn handle_derotation(&mut self) {
+        // The flare is over, the next one memorises its attitude again
+        self.flare_effect = 0.0;
+        self.memorised_attitude = Angle::default();
+        self.flare_attitude = Angle::default();
+    }
+
+    fn handle_ground(&mut self) {
+        self.flare_effect = 0.0;
+    }
+
+    fn handle_flight(&mut self, dt: f64) {
+        // Blend back to flight mode, e.g. on a go around from the flare
+        self.flare_effect = (self.flare_effect - dt / Self::FLARE_TRANSITION_TIME).max(0.0);
+    }
+
+    fn handle_flare(&mut self, inputs: &PitchControlInputs) {
+        let dt = inputs.dt;
+        if self.mode != PitchControlMode::Flare {
+            // Memorise the attitude when entering flare mode
+            self.memorised_attitude = inputs.pitch;
+            self.flare_attitude = self.memorised_attitude;
+        }
+        self.flare_effect = (self.flare_effect + dt / Self::FLARE_TRANSITION_TIME).min(1.0);
+
+        if inputs.radio_height < Self::FLARE_PITCH_DOWN_HEIGHT {
+            let pitch_down_rate = AngularVelocity::from_change(
+                self.memorised_attitude - Self::FLARE_PITCH_DOWN_ATTITUDE,
+                Self::FLARE_PITCH_DOWN_TIME,
+            )
+            .max(AngularVelocity::default());
+            self.flare_attitude = (self.flare_attitude - Angle::swept(pitch_down_rate, dt))
+                .max(Self::FLARE_PITCH_DOWN_ATTITUDE.min(self.memorised_attitude));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    const DT: f64 = 0.1;
+
+    // Runs the mode selection for a duration, returning the mode at the end
+    fn hold(
+        control: &mut PitchControl,
+        phase: FlightPhase,
+        on_ground: bool,
+        nose_gear_on_ground: bool,
+        seconds: f64,
+    ) -> PitchControlMode {
+        for _ in 0..(seconds / DT).round() as usize {
+            control.mode = control.select_mode(phase, on_ground, nose_gear_on_ground, DT);
         }
-         */
+        control.mode
     }
 
-    fn handle_flight_transitions(&mut self) {}
+    fn flaring() -> PitchControl {
+        let mut control = PitchControl::default();
+        hold(&mut control, FlightPhase::Flare, false, false, DT);
+        control
+    }
 
-    fn handle_flare_transitions(&mut self) {}
+    #[test]
+    fn ground_mode_waits_for_the_nose_gear() {
+        let mut control = flaring();
+        assert_eq!(control.mode, PitchControlMode::Flare);
+
+        // Main gear touchdown, the phase changes to the rollout once the touchdown is confirmed
+        assert_eq!(
+            hold(&mut control, FlightPhase::Flare, true, false, 0.5),
+            PitchControlMode::Derotation
+        );
+        assert_eq!(
+            hold(&mut control, FlightPhase::Rollout, true, false, 2.0),
+            PitchControlMode::Derotation
+        );
+
+        // Nose gear touchdown
+        assert_eq!(
+            hold(&mut control, FlightPhase::Rollout, true, true, DT),
+            PitchControlMode::Ground
+        );
+        // And ground mode is kept if the nose bounces
+        assert_eq!(
+            hold(&mut control, FlightPhase::Rollout, true, false, DT),
+            PitchControlMode::Ground
+        );
+    }
+
+    #[test]
+    fn derotation_times_out() {
+        let mut control = flaring();
+
+        assert_eq!(
+            hold(&mut control, FlightPhase::Rollout, true, false, 4.5),
+            PitchControlMode::Derotation
+        );
+        assert_eq!(
+            hold(&mut control, FlightPhase::Rollout, true, false, 1.0),
+            PitchControlMode::Ground
+        );
+    }
+
+    #[test]
+    fn no_derotation_without_a_flare() {
+        let mut control = PitchControl::default();
+        hold(&mut control, FlightPhase::Climb, false, false, DT);
+
+        assert_eq!(
+            hold(&mut control, FlightPhase::Rollout, true, false, DT),
+            PitchControlMode::Ground
+        );
+        // Nor from a bounce back into the air
+        let mut control = flaring();
+        assert_eq!(
+            hold(&mut control, FlightPhase::Flare, false, false, 1.0),
+            PitchControlMode::Flare
+        );
+    }
 }
@@ -1,32 +1,39 @@
-use crate::{fbw::FBW, Result};
+use crate::{
+    autopilot::{PitchCommand, RollCommand},
+    clamp,
+    fbw::FBW,
+    limits::Configuration,
+    units::{Angle, AngularVelocity},
+    Result,
+};
 
 #[derive(Default, Clone)]
 pub(crate) struct NormalLawProtections {
     pub(crate) aoa_demand_active: bool,
     pub(crate) aoa_demand_deactivation_timer: f64,
     pub(crate) high_speed_protection_active: bool,
-    pub(crate) max_bank_angle: f64,
-    pub(crate) nominal_bank_angle: f64,
+    pub(crate) max_bank_angle: Angle,
+    pub(crate) nominal_bank_angle: Angle,
     pub(crate) min_load_factor: f64,
     pub(crate) max_load_factor: f64,
-    pub(crate) min_pitch_angle: f64,
-    pub(crate) max_pitch_angle: f64,
+    pub(crate) min_pitch_angle: Angle,
+    pub(crate) max_pitch_angle: Angle,
 }
 
 impl NormalLawProtections {
-    // Maximum bank angle in degrees
+    // Maximum bank angle
     // - Normally: 67 degrees
     // - High Angle of Attack Protection: 45 degrees
     // - High Speed Protection: 45 degrees
-    const MAX_BANK_ANGLE_NORMAL: f64 = 67.0;
-    const MAX_BANK_ANGLE_PROTECTED: f64 = 45.0;
+    const MAX_BANK_ANGLE_NORMAL: Angle = Angle::from_degrees(67.0);
+    const MAX_BANK_ANGLE_PROTECTED: Angle = Angle::from_degrees(45.0);
 
-    // Spiral static stability in degrees
+    // Spiral static stability
     // - Normally: 33 degrees
     // - High Angle of Attack Protection: 0 degrees
     // - High Speed Protection: 0 degrees
-    const NOMINAL_BANK_ANGLE_NORMAL: f64 = 33.0;
-    const NOMINAL_BANK_ANGLE_PROTECTED: f64 = 0.0;
+    const NOMINAL_BANK_ANGLE_NORMAL: Angle = Angle::from_degrees(33.0);
+    const NOMINAL_BANK_ANGLE_PROTECTED: Angle = Angle::from_degrees(0.0);
 
     // -1g for clean configuration, 0g for other configurations
     const MIN_LOAD_FACTOR_NORMAL: f64 = -1.0;
@@ -35,17 +42,20 @@ impl NormalLawProtections {
     const MAX_LOAD_FACTOR_NORMAL: f64 = 2.5;
     const MAX_LOAD_FACTOR_PROTECTED: f64 = 2.0;
 
-    // Maximum pitch attitude in degrees
+    // Maximum pitch attitude
     // 30 degrees nose up in conf 0-3 (progressively reduced to 25 degrees at low speed)
     // 25 degrees nose up in conf FULL (progressively reduced to 20 degrees at low speed)
     // TODO: To implement the 'progressively reduced' limitation, we need to find a way to
     //       calculate speeds like V_alpha_prot or V_alpha_max, for which I think we will
     //       have to work out via experimentation.
-    const MAX_PITCH_ANGLE_NORMAL: f64 = 30.0;
-    const MAX_PITCH_ANGLE_PROTECTED: f64 = 25.0;
+    const MAX_PITCH_ANGLE_NORMAL: Angle = Angle::from_degrees(30.0);
+    const MAX_PITCH_ANGLE_PROTECTED: Angle = Angle::from_degrees(25.0);
 
-    // Minimum pitch attitude in degrees
-    const MIN_PITCH_ANGLE_NORMAL: f64 = -15.0;
+    // Minimum pitch attitude
+    const MIN_PITCH_ANGLE_NORMAL: Angle = Angle::from_degrees(-15.0);
+
+    // The same roll rate as a full sidestick deflection
+    const MAX_ROLL_RATE: AngularVelocity = AngularVelocity::from_degrees_per_second(15.0);
 }
 
 impl NormalLawProtections {
@@ -62,7 +72,7 @@ impl NormalLawProtections {
             if condition1 || condition2 {
                 self.aoa_demand_active = false;
                 self.aoa_demand_deactivation_timer = 0.0;
-            } else if ctx.input.yoke_y < 0.0 && ctx.data.alpha() < ctx.data.alpha_max() {
+            } else if ctx.input.yoke_y < 0.0 && ctx.adr.alpha() < ctx.data.alpha_max() {
                 // We're still building the target duration to meet condition 2
                 self.aoa_demand_deactivation_timer += dt;
             } else {
@@ -72,9 +82,9 @@ impl NormalLawProtections {
         } else {
             // Should we enter AoA demand mode?
             // Enter condition 1: Sidestick must not be pushed down, and AoA is greater than alpha_prot
-            let condition1 = ctx.input.yoke_y >= 0.0 && ctx.data.alpha() > ctx.data.alpha_prot();
+            let condition1 = ctx.input.yoke_y >= 0.0 && ctx.adr.alpha() > ctx.data.alpha_prot();
             // Enter condition 2: We are at or above alpha max
-            let condition2 = ctx.data.alpha() >= ctx.data.alpha_max();
+            let condition2 = ctx.adr.alpha() >= ctx.data.alpha_max();
             if condition1 || condition2 {
                 self.aoa_demand_active = true;
                 self.aoa_demand_deactivation_timer = 0.0;
@@ -83,7 +93,7 @@ impl NormalLawProtections {
 
         // Check if high speed protection is active
         self.high_speed_protection_active =
-            ctx.data.ias() > ctx.data.vmo() || ctx.data.mach() > ctx.data.mmo();
+            ctx.adr.ias() > ctx.speed_limits.vmo || ctx.data.mach() > ctx.speed_limits.mmo;
 
         // Update bank angle limits
         if self.aoa_demand_active || self.high_speed_protection_active {
@@ -94,27 +104,140 @@ impl NormalLawProtections {
             self.nominal_bank_angle = Self::NOMINAL_BANK_ANGLE_NORMAL;
         }
 
-        // Update load and pitch factors
+        self.set_configuration_limits(ctx.speed_limits.configuration);
+
+        Ok(())
+    }
+
+    // Updates the load factor and pitch attitude limits of a slats/flaps configuration
+    fn set_configuration_limits(&mut self, configuration: Configuration) {
         self.min_pitch_angle = Self::MIN_PITCH_ANGLE_NORMAL;
-        match ctx.data.flaps() {
-            0 => {
+        match configuration {
+            Configuration::Clean => {
                 self.min_load_factor = Self::MIN_LOAD_FACTOR_NORMAL;
                 self.max_load_factor = Self::MAX_LOAD_FACTOR_NORMAL;
                 self.max_pitch_angle = Self::MAX_PITCH_ANGLE_NORMAL;
             }
-            1 | 2 | 3 => {
+            Configuration::Conf1
+            | Configuration::Conf1F
+            | Configuration::Conf2
+            | Configuration::Conf3 => {
                 self.min_load_factor = Self::MIN_LOAD_FACTOR_PROTECTED;
                 self.max_load_factor = Self::MAX_LOAD_FACTOR_PROTECTED;
                 self.max_pitch_angle = Self::MAX_PITCH_ANGLE_NORMAL;
             }
-            4 => {
+            Configuration::Full => {
                 self.min_load_factor = Self::MIN_LOAD_FACTOR_PROTECTED;
                 self.max_load_factor = Self::MAX_LOAD_FACTOR_PROTECTED;
                 self.max_pitch_angle = Self::MAX_PITCH_ANGLE_PROTECTED;
             }
-            _ => unreachable!(),
         };
+    }
 
-        Ok(())
+    // Bounds an autopilot pitch command to the normal law flight envelope
+    pub(crate) fn limit_pitch_command(&self, command: PitchCommand) -> PitchCommand {
+        match command {
+            PitchCommand::Attitude(pitch) => {
+                PitchCommand::Attitude(pitch.clamp(self.min_pitch_angle, self.max_pitch_angle))
+            }
+            PitchCommand::LoadFactor(load_factor) => PitchCommand::LoadFactor(clamp(
+                load_factor,
+                self.min_load_factor,
+                self.max_load_factor,
+            )),
+        }
+    }
+
+    // Bounds an autopilot roll command to the normal law flight envelope
+    pub(crate) fn limit_roll_command(&self, command: RollCommand) -> RollCommand {
+        match command {
+            RollCommand::BankAngle(bank) => {
+                RollCommand::BankAngle(bank.clamp(-self.max_bank_angle, self.max_bank_angle))
+            }
+            RollCommand::RollRate(rate) => {
+                RollCommand::RollRate(rate.clamp(-Self::MAX_ROLL_RATE, Self::MAX_ROLL_RATE))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean() -> NormalLawProtections {
+        NormalLawProtections {
+            max_bank_angle: NormalLawProtections::MAX_BANK_ANGLE_NORMAL,
+            min_load_factor: NormalLawProtections::MIN_LOAD_FACTOR_NORMAL,
+            max_load_factor: NormalLawProtections::MAX_LOAD_FACTOR_NORMAL,
+            min_pitch_angle: NormalLawProtections::MIN_PITCH_ANGLE_NORMAL,
+            max_pitch_angle: NormalLawProtections::MAX_PITCH_ANGLE_NORMAL,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn autopilot_pitch_commands_are_bounded() {
+        let protections = clean();
+        let attitude = |degrees| PitchCommand::Attitude(Angle::from_degrees(degrees));
+
+        assert_eq!(
+            protections.limit_pitch_command(attitude(5.0)),
+            attitude(5.0)
+        );
+        assert_eq!(
+            protections.limit_pitch_command(attitude(40.0)),
+            attitude(30.0)
+        );
+        assert_eq!(
+            protections.limit_pitch_command(attitude(-20.0)),
+            attitude(-15.0)
+        );
+        assert_eq!(
+            protections.limit_pitch_command(PitchCommand::LoadFactor(1.2)),
+            PitchCommand::LoadFactor(1.2)
+        );
+        assert_eq!(
+            protections.limit_pitch_command(PitchCommand::LoadFactor(3.0)),
+            PitchCommand::LoadFactor(2.5)
+        );
+        assert_eq!(
+            protections.limit_pitch_command(PitchCommand::LoadFactor(-2.0)),
+            PitchCommand::LoadFactor(-1.0)
+        );
+    }
+
+    #[test]
+    fn autopilot_roll_commands_are_bounded() {
+        let protections = clean();
+        let bank = |degrees| RollCommand::BankAngle(Angle::from_degrees(degrees));
+        let rate =
+            |degrees| RollCommand::RollRate(AngularVelocity::from_degrees_per_second(degrees));
+
+        assert_eq!(protections.limit_roll_command(bank(25.0)), bank(25.0));
+        assert_eq!(protections.limit_roll_command(bank(80.0)), bank(67.0));
+        assert_eq!(protections.limit_roll_command(bank(-80.0)), bank(-67.0));
+        assert_eq!(protections.limit_roll_command(rate(5.0)), rate(5.0));
+        assert_eq!(protections.limit_roll_command(rate(-20.0)), rate(-15.0));
+    }
+
+    #[test]
+    fn configuration_limits() {
+        let limits = |handle| {
+            let mut protections = NormalLawProtections::default();
+            protections.set_configuration_limits(Configuration::from_handle(handle, handle > 0));
+            (
+                protections.min_load_factor,
+                protections.max_load_factor,
+                protections.max_pitch_angle.degrees(),
+            )
+        };
+
+        assert_eq!(limits(0), (-1.0, 2.5, 30.0));
+        assert_eq!(limits(1), (0.0, 2.0, 30.0));
+        assert_eq!(limits(3), (0.0, 2.0, 30.0));
+        assert_eq!(limits(4), (0.0, 2.0, 25.0));
+        // A handle position beyond FULL is flown as FULL
+        assert_eq!(limits(5), (0.0, 2.0, 25.0));
     }
 }
@@ -0,0 +1,215 @@
+use crate::clamp;
+use msfs::msfs::legacy::NamedVariable;
+
+// The shaping L:vars of an axis, registered once
+pub(crate) struct AxisConfig {
+    deadband: NamedVariable,
+    curve: NamedVariable,
+}
+
+impl AxisConfig {
+    pub(crate) fn new(axis: &str) -> Self {
+        AxisConfig {
+            deadband: NamedVariable::from(&format!("A32NX_FBW_{}_DEADBAND", axis)),
+            curve: NamedVariable::from(&format!("A32NX_FBW_{}_CURVE", axis)),
+        }
+    }
+
+    pub(crate) fn shaping(&self) -> AxisShaping {
+        AxisShaping::new(
+            self.deadband.get_value::<f64>(),
+            self.curve.get_value::<f64>(),
+        )
+    }
+}
+
+// Deadband and response curve of an input axis, configured through the
+// A32NX_FBW_<AXIS>_DEADBAND and A32NX_FBW_<AXIS>_CURVE L:vars.
+// Both default to 0.0, which leaves the axis linear.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct AxisShaping {
+    deadband: f64, // Fraction of the travel around neutral that is ignored [0, 0.5]
+    curve: f64,    // Blend between a linear (0.0) and a cubic (1.0) response [0, 1]
+}
+
+impl AxisShaping {
+    pub(crate) fn new(deadband: f64, curve: f64) -> Self {
+        AxisShaping {
+            deadband: clamp(deadband, 0.0, 0.5),
+            curve: clamp(curve, 0.0, 1.0),
+        }
+    }
+
+    pub(crate) fn apply(&self, x: f64) -> f64 {
+        if x.abs() <= self.deadband {
+            return 0.0;
+        }
+        // Rescale what is left outside of the deadband, so that the full travel is still available
+        let x = x.signum() * (x.abs() - self.deadband) / (1.0 - self.deadband);
+        (1.0 - self.curve) * x + self.curve * x.powi(3)
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Slew {
+    None,     // Analog input, the axis follows it directly
+    Keyboard, // Digital input, optionally smoothed
+    Centring, // Returning to neutral, as if released on its centring spring
+}
+
+#[derive(Clone)]
+pub(crate) struct InputAxis {
+    target: f64,
+    position: f64,
+    slew: Slew,
+}
+impl Default for InputAxis {
+    fn default() -> Self {
+        InputAxis {
+            target: 0.0,
+            position: 0.0,
+            slew: Slew::None,
+        }
+    }
+}
+
+impl InputAxis {
+    // Travel added by each keyboard (or other digital) input
+    const KEYBOARD_STEP: f64 = 0.1;
+    // Rate (in position per second) at which the centring spring brings the axis back to neutral
+    const CENTRING_RATE: f64 = 4.0;
+
+    // Sets the axis from an analog source
+    pub(crate) fn set(&mut self, value: f64) {
+        self.target = value;
+        self.position = value;
+        self.slew = Slew::None;
+    }
+
+    // Moves the axis by one keyboard step in the given direction
+    pub(crate) fn step(&mut self, direction: f64) {
+        self.target = clamp(self.target + direction * Self::KEYBOARD_STEP, -1.0, 1.0);
+        self.slew = Slew::Keyboard;
+    }
+
+    // Releases the axis back to neutral
+    pub(crate) fn center(&mut self) {
+        self.target = 0.0;
+        self.slew = Slew::Centring;
+    }
+
+    // Advances the axis towards its target, keyboard_rate is in position per second and
+    // disables the keyboard smoothing when 0.0
+    pub(crate) fn update(&mut self, keyboard_rate: f64, dt: f64) -> f64 {
+        let rate = match self.slew {
+            Slew::None => None,
+            Slew::Keyboard if keyboard_rate > 0.0 => Some(keyboard_rate),
+            Slew::Keyboard => None,
+            Slew::Centring => Some(Self::CENTRING_RATE),
+        };
+
+        self.position = match rate {
+            Some(rate) => {
+                let travel = rate * dt;
+                self.position + (self.target - self.position).max(-travel).min(travel)
+            }
+            None => self.target,
+        };
+
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    const DT: f64 = 0.05;
+
+    // Updates an axis for a number of seconds, returning its last position
+    fn run(axis: &mut InputAxis, keyboard_rate: f64, seconds: f64) -> f64 {
+        let mut position = axis.position;
+        for _ in 0..(seconds / DT).round() as usize {
+            position = axis.update(keyboard_rate, DT);
+        }
+        position
+    }
+
+    #[test]
+    fn linear_by_default() {
+        let shaping = AxisShaping::default();
+
+        for &x in [-1.0, -0.4, 0.0, 0.05, 0.7, 1.0].iter() {
+            assert_close(shaping.apply(x), x, 1e-12);
+        }
+    }
+
+    #[test]
+    fn deadband_is_rescaled() {
+        let shaping = AxisShaping::new(0.1, 0.0);
+
+        assert_close(shaping.apply(0.05), 0.0, 1e-12);
+        assert_close(shaping.apply(-0.1), 0.0, 1e-12);
+        // The travel left outside of the deadband still reaches full deflection
+        assert_close(shaping.apply(0.55), 0.5, 1e-12);
+        assert_close(shaping.apply(-0.55), -0.5, 1e-12);
+        assert_close(shaping.apply(1.0), 1.0, 1e-12);
+        assert_close(shaping.apply(-1.0), -1.0, 1e-12);
+
+        // The configuration is bounded
+        assert_close(AxisShaping::new(0.9, 0.0).apply(0.75), 0.5, 1e-12);
+    }
+
+    #[test]
+    fn curve_blend() {
+        // Cubic, then halfway between linear and cubic
+        assert_close(AxisShaping::new(0.0, 1.0).apply(0.5), 0.125, 1e-12);
+        assert_close(AxisShaping::new(0.0, 0.5).apply(0.5), 0.3125, 1e-12);
+        assert_close(AxisShaping::new(0.0, 0.5).apply(-0.5), -0.3125, 1e-12);
+        assert_close(AxisShaping::new(0.0, 2.0).apply(1.0), 1.0, 1e-12);
+
+        // The curve applies to the rescaled travel
+        assert_close(AxisShaping::new(0.2, 1.0).apply(0.6), 0.125, 1e-12);
+    }
+
+    #[test]
+    fn analog_input_is_direct() {
+        let mut axis = InputAxis::default();
+        axis.set(0.8);
+
+        assert_close(axis.update(0.5, DT), 0.8, 1e-12);
+    }
+
+    #[test]
+    fn keyboard_slew() {
+        let mut axis = InputAxis::default();
+        for _ in 0..5 {
+            axis.step(1.0);
+        }
+
+        // Slewed at the keyboard rate
+        assert_close(run(&mut axis, 0.5, 0.4), 0.2, 1e-12);
+        assert_close(run(&mut axis, 0.5, 1.0), 0.5, 1e-12);
+        // Or moved by whole steps without smoothing
+        axis.step(-1.0);
+        assert_close(axis.update(0.0, DT), 0.4, 1e-12);
+
+        // The steps are bounded to the travel
+        for _ in 0..20 {
+            axis.step(-1.0);
+        }
+        assert_close(axis.update(0.0, DT), -1.0, 1e-12);
+    }
+
+    #[test]
+    fn centring() {
+        let mut axis = InputAxis::default();
+        axis.set(-1.0);
+        axis.center();
+
+        // The spring brings it back at 4 per second, even with the keyboard smoothing disabled
+        assert_close(run(&mut axis, 0.0, 0.1), -0.6, 1e-12);
+        assert_close(run(&mut axis, 0.0, 0.5), 0.0, 1e-12);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::clamp;
+
+#[derive(Default, Clone, Copy)]
+pub(crate) struct Sidestick {
+    pub(crate) x: f64,         // -1 is full left, and +1 is full right
+    pub(crate) y: f64,         // -1 is full down, and +1 is full up
+    pub(crate) takeover: bool, // True while the takeover pushbutton is held
+}
+
+impl Sidestick {
+    // Deflection beyond which the sidestick is considered out of neutral (about 2 degrees)
+    const DEFLECTION_THRESHOLD: f64 = 0.1;
+
+    fn deflected(&self) -> bool {
+        self.x.abs() > Self::DEFLECTION_THRESHOLD || self.y.abs() > Self::DEFLECTION_THRESHOLD
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum Priority {
+    None,
+    Captain,
+    FirstOfficer,
+}
+
+// Sidestick priority logic, from the FCOM in 1.27.40 under "Sidesticks"
+#[derive(Clone)]
+pub(crate) struct SidestickPriority {
+    pub(crate) priority: Priority,
+    pub(crate) dual_input: bool,
+    latched: bool,
+    takeover_timer: f64,
+    captain_takeover: bool,
+    first_officer_takeover: bool,
+}
+impl Default for SidestickPriority {
+    fn default() -> Self {
+        SidestickPriority {
+            priority: Priority::None,
+            dual_input: false,
+            latched: false,
+            takeover_timer: 0.0,
+            captain_takeover: false,
+            first_officer_takeover: false,
+        }
+    }
+}
+
+impl SidestickPriority {
+    // Holding the takeover pushbutton for this long (in seconds) latches the priority
+    const LATCH_TIME: f64 = 40.0;
+
+    // Returns the resulting (x, y) sidestick order
+    pub(crate) fn update(
+        &mut self,
+        captain: &Sidestick,
+        first_officer: &Sidestick,
+        dt: f64,
+    ) -> (f64, f64) {
+        // The last pilot to press their takeover pushbutton gets priority, which also cancels a
+        // latched priority from the other side
+        if captain.takeover && !self.captain_takeover {
+            self.take_priority(Priority::Captain);
+        }
+        if first_officer.takeover && !self.first_officer_takeover {
+            self.take_priority(Priority::FirstOfficer);
+        }
+        self.captain_takeover = captain.takeover;
+        self.first_officer_takeover = first_officer.takeover;
+
+        let held = match self.priority {
+            Priority::Captain => captain.takeover,
+            Priority::FirstOfficer => first_officer.takeover,
+            Priority::None => false,
+        };
+        if held {
+            self.takeover_timer += dt;
+            if self.takeover_timer >= Self::LATCH_TIME {
+                self.latched = true;
+            }
+        } else if !self.latched {
+            self.priority = Priority::None;
+            self.takeover_timer = 0.0;
+        }
+
+        // Without priority, both sidestick orders are algebraically added, and limited to the
+        // order of a single sidestick at full deflection
+        self.dual_input =
+            self.priority == Priority::None && captain.deflected() && first_officer.deflected();
+        match self.priority {
+            Priority::Captain => (captain.x, captain.y),
+            Priority::FirstOfficer => (first_officer.x, first_officer.y),
+            Priority::None => (
+                clamp(captain.x + first_officer.x, -1.0, 1.0),
+                clamp(captain.y + first_officer.y, -1.0, 1.0),
+            ),
+        }
+    }
+
+    fn take_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+        self.latched = false;
+        self.takeover_timer = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.05;
+
+    fn stick(x: f64, y: f64) -> Sidestick {
+        Sidestick {
+            x,
+            y,
+            takeover: false,
+        }
+    }
+
+    fn takeover(x: f64, y: f64) -> Sidestick {
+        Sidestick {
+            x,
+            y,
+            takeover: true,
+        }
+    }
+
+    // Runs the priority logic for a number of seconds, returning the last order
+    fn run(
+        priority: &mut SidestickPriority,
+        captain: Sidestick,
+        first_officer: Sidestick,
+        seconds: f64,
+    ) -> (f64, f64) {
+        let mut order = (0.0, 0.0);
+        for _ in 0..(seconds / DT).round() as usize {
+            order = priority.update(&captain, &first_officer, DT);
+        }
+        order
+    }
+
+    #[test]
+    fn single_input() {
+        let mut priority = SidestickPriority::default();
+
+        assert_eq!(
+            run(&mut priority, stick(0.5, -0.3), stick(0.0, 0.0), DT),
+            (0.5, -0.3)
+        );
+        assert_eq!(
+            run(&mut priority, stick(0.0, 0.0), stick(-0.2, 0.4), DT),
+            (-0.2, 0.4)
+        );
+        assert!(!priority.dual_input);
+    }
+
+    #[test]
+    fn dual_input_is_summed_and_limited() {
+        let mut priority = SidestickPriority::default();
+
+        assert_eq!(
+            run(&mut priority, stick(0.25, 0.5), stick(0.25, -0.25), DT),
+            (0.5, 0.25)
+        );
+        assert!(priority.dual_input);
+
+        assert_eq!(
+            run(&mut priority, stick(0.75, -0.75), stick(0.5, -1.0), DT),
+            (1.0, -1.0)
+        );
+        assert!(priority.dual_input);
+    }
+
+    #[test]
+    fn small_deflections_are_not_dual_input() {
+        let mut priority = SidestickPriority::default();
+        run(&mut priority, stick(0.5, 0.0), stick(0.05, 0.05), DT);
+
+        assert!(!priority.dual_input);
+    }
+
+    #[test]
+    fn takeover_while_held() {
+        let mut priority = SidestickPriority::default();
+
+        let order = run(&mut priority, stick(0.5, 0.5), takeover(-0.25, 0.0), 1.0);
+        assert_eq!(order, (-0.25, 0.0));
+        assert_eq!(priority.priority, Priority::FirstOfficer);
+        assert!(!priority.dual_input);
+
+        // Releasing the pushbutton before it latches returns to dual input
+        let order = run(&mut priority, stick(0.5, 0.5), stick(-0.25, 0.0), DT);
+        assert_eq!(order, (0.25, 0.5));
+        assert_eq!(priority.priority, Priority::None);
+    }
+
+    #[test]
+    fn last_takeover_wins() {
+        let mut priority = SidestickPriority::default();
+        run(&mut priority, takeover(0.5, 0.0), stick(0.0, 0.0), 1.0);
+        assert_eq!(priority.priority, Priority::Captain);
+
+        let order = run(&mut priority, takeover(0.5, 0.0), takeover(-0.5, 0.0), 1.0);
+        assert_eq!(order, (-0.5, 0.0));
+        assert_eq!(priority.priority, Priority::FirstOfficer);
+    }
+
+    #[test]
+    fn takeover_latches_after_40_seconds() {
+        let mut priority = SidestickPriority::default();
+        run(&mut priority, takeover(0.0, 0.0), stick(0.0, 0.0), 39.0);
+        assert!(!priority.latched);
+
+        run(&mut priority, takeover(0.0, 0.0), stick(0.0, 0.0), 1.0 + DT);
+        assert!(priority.latched);
+
+        // The captain keeps priority after releasing the pushbutton
+        let order = run(&mut priority, stick(0.0, 0.0), stick(1.0, 1.0), 10.0);
+        assert_eq!(order, (0.0, 0.0));
+        assert_eq!(priority.priority, Priority::Captain);
+
+        // Until the first officer takes it back
+        let order = run(&mut priority, stick(0.0, 0.0), takeover(1.0, 1.0), DT);
+        assert_eq!(order, (1.0, 1.0));
+        assert_eq!(priority.priority, Priority::FirstOfficer);
+        assert!(!priority.latched);
+    }
+}
@@ -0,0 +1,596 @@
+// Stateful discrete time blocks to build the control laws from.
+// Each block is updated once per frame with the frame delta time, which is not constant.
+//
+// Not every block is used by the laws yet
+#![allow(dead_code)]
+
+use crate::Result;
+use std::f64::consts::PI;
+
+// First order low pass filter: H(s) = 1 / (tau * s + 1)
+#[derive(Clone)]
+pub(crate) struct LowPass {
+    time_constant: f64,
+    output: Option<f64>,
+}
+
+impl LowPass {
+    pub(crate) fn new(time_constant: f64) -> LowPass {
+        LowPass {
+            time_constant,
+            output: None,
+        }
+    }
+
+    pub(crate) fn from_cutoff(frequency: f64) -> LowPass {
+        LowPass::new(1.0 / (2.0 * PI * frequency))
+    }
+
+    pub(crate) fn set_cutoff(&mut self, frequency: f64) {
+        self.time_constant = 1.0 / (2.0 * PI * frequency);
+    }
+
+    pub(crate) fn update(&mut self, input: f64, dt: f64) -> f64 {
+        // The first sample initialises the filter, so it doesn't have to settle from 0.0
+        let output = match self.output {
+            Some(output) if self.time_constant > 0.0 => {
+                output + dt / (self.time_constant + dt) * (input - output)
+            }
+            _ => input,
+        };
+        self.output = Some(output);
+        output
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.output = None;
+    }
+}
+
+// First order washout (high pass) filter: H(s) = tau * s / (tau * s + 1)
+#[derive(Clone)]
+pub(crate) struct Washout {
+    low_pass: LowPass,
+}
+
+impl Washout {
+    pub(crate) fn new(time_constant: f64) -> Washout {
+        Washout {
+            low_pass: LowPass::new(time_constant),
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: f64, dt: f64) -> f64 {
+        input - self.low_pass.update(input, dt)
+    }
+}
+
+// Lead-lag compensator: H(s) = (lead * s + 1) / (lag * s + 1), discretised with Tustin's method
+#[derive(Clone)]
+pub(crate) struct LeadLag {
+    lead: f64,
+    lag: f64,
+    last_input: Option<f64>,
+    output: f64,
+}
+
+impl LeadLag {
+    pub(crate) fn new(lead: f64, lag: f64) -> LeadLag {
+        LeadLag {
+            lead,
+            lag,
+            last_input: None,
+            output: 0.0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: f64, dt: f64) -> f64 {
+        self.output = match self.last_input {
+            Some(last_input) if dt > 0.0 => {
+                ((2.0 * self.lead + dt) * input + (dt - 2.0 * self.lead) * last_input
+                    - (dt - 2.0 * self.lag) * self.output)
+                    / (2.0 * self.lag + dt)
+            }
+            // Start from steady state
+            None => input,
+            _ => self.output,
+        };
+        self.last_input = Some(input);
+        self.output
+    }
+}
+
+// Second order notch filter:
+// H(s) = (s^2 + 2 * zeta_zero * w * s + w^2) / (s^2 + 2 * zeta_pole * w * s + w^2)
+// discretised with Tustin's method, prewarped at the notch frequency.
+#[derive(Clone)]
+pub(crate) struct Notch {
+    frequency: f64, // Notch frequency in hertz
+    zeta_zero: f64, // Damping of the zeros, sets the notch depth
+    zeta_pole: f64, // Damping of the poles, sets the notch width
+    inputs: Option<[f64; 2]>,
+    outputs: [f64; 2],
+}
+
+impl Notch {
+    pub(crate) fn new(frequency: f64, zeta_zero: f64, zeta_pole: f64) -> Notch {
+        Notch {
+            frequency,
+            zeta_zero,
+            zeta_pole,
+            inputs: None,
+            outputs: [0.0; 2],
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: f64, dt: f64) -> f64 {
+        let inputs = match self.inputs {
+            Some(inputs) if dt > 0.0 => inputs,
+            Some(_) => return self.outputs[0],
+            // Start from steady state, the notch has unity gain at DC
+            None => {
+                self.inputs = Some([input; 2]);
+                self.outputs = [input; 2];
+                return input;
+            }
+        };
+
+        // Prewarp so the notch stays at its frequency whatever the frame rate.
+        // The notch can't be above the Nyquist frequency.
+        let w = 2.0 * PI * self.frequency.min(0.45 / dt);
+        let k = w / (w * dt / 2.0).tan();
+        let (k2, w2) = (k * k, w * w);
+
+        let b0 = k2 + 2.0 * self.zeta_zero * w * k + w2;
+        let b1 = 2.0 * (w2 - k2);
+        let b2 = k2 - 2.0 * self.zeta_zero * w * k + w2;
+        let a0 = k2 + 2.0 * self.zeta_pole * w * k + w2;
+        let a1 = 2.0 * (w2 - k2);
+        let a2 = k2 - 2.0 * self.zeta_pole * w * k + w2;
+
+        let output = (b0 * input + b1 * inputs[0] + b2 * inputs[1]
+            - a1 * self.outputs[0]
+            - a2 * self.outputs[1])
+            / a0;
+
+        self.inputs = Some([input, inputs[0]]);
+        self.outputs = [output, self.outputs[0]];
+        output
+    }
+}
+
+// Limits the rate of change of a signal
+#[derive(Clone)]
+pub(crate) struct RateLimiter {
+    rate_up: f64,   // Maximum rate when increasing (per second)
+    rate_down: f64, // Maximum rate when decreasing (per second)
+    output: Option<f64>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: f64) -> RateLimiter {
+        RateLimiter::asymmetric(rate, rate)
+    }
+
+    pub(crate) fn asymmetric(rate_up: f64, rate_down: f64) -> RateLimiter {
+        RateLimiter {
+            rate_up,
+            rate_down,
+            output: None,
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: f64, dt: f64) -> f64 {
+        let output = match self.output {
+            Some(output) => {
+                output
+                    + (input - output)
+                        .max(-self.rate_down * dt)
+                        .min(self.rate_up * dt)
+            }
+            None => input,
+        };
+        self.output = Some(output);
+        output
+    }
+
+    pub(crate) fn reset(&mut self, value: f64) {
+        self.output = Some(value);
+    }
+}
+
+// Switches on above the high threshold, and back off below the low threshold
+#[derive(Clone)]
+pub(crate) struct Hysteresis {
+    low: f64,
+    high: f64,
+    output: bool,
+}
+
+impl Hysteresis {
+    pub(crate) fn new(low: f64, high: f64) -> Hysteresis {
+        Hysteresis {
+            low,
+            high,
+            output: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: f64) -> bool {
+        if input > self.high {
+            self.output = true;
+        } else if input < self.low {
+            self.output = false;
+        }
+        self.output
+    }
+}
+
+// Confirms a condition once it has been continuously true for a given time
+#[derive(Clone)]
+pub(crate) struct ConfirmTimer {
+    time: f64, // Confirmation time in seconds
+    elapsed: f64,
+}
+
+impl ConfirmTimer {
+    pub(crate) fn new(time: f64) -> ConfirmTimer {
+        ConfirmTimer { time, elapsed: 0.0 }
+    }
+
+    pub(crate) fn update(&mut self, condition: bool, dt: f64) -> bool {
+        if condition {
+            self.elapsed += dt;
+        } else {
+            self.elapsed = 0.0;
+        }
+        self.confirmed()
+    }
+
+    pub(crate) fn confirmed(&self) -> bool {
+        self.elapsed >= self.time
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+// Set/reset latch, the reset has priority
+#[derive(Default, Clone)]
+pub(crate) struct Latch {
+    output: bool,
+}
+
+impl Latch {
+    pub(crate) fn update(&mut self, set: bool, reset: bool) -> bool {
+        if reset {
+            self.output = false;
+        } else if set {
+            self.output = true;
+        }
+        self.output
+    }
+}
+
+// Finds the breakpoint below the value, and the interpolation coefficient to the next one.
+// Values outside of the axis are clamped to its edges.
+fn locate(axis: &[f64], value: f64) -> (usize, f64) {
+    if axis.len() == 1 || value <= axis[0] {
+        return (0, 0.0);
+    }
+    for i in 0..axis.len() - 1 {
+        if value <= axis[i + 1] {
+            return (i, (value - axis[i]) / (axis[i + 1] - axis[i]));
+        }
+    }
+    (axis.len() - 1, 0.0)
+}
+
+// Linear interpolation of a value over a line
+#[derive(Clone)]
+pub(crate) struct Table1D {
+    x: Vec<f64>,
+    values: Vec<f64>,
+}
+
+impl Table1D {
+    // The points must be sorted by increasing x
+    pub(crate) fn new(points: &[(f64, f64)]) -> Table1D {
+        debug_assert!(!points.is_empty());
+        debug_assert!(points.windows(2).all(|p| p[0].0 < p[1].0));
+        Table1D {
+            x: points.iter().map(|p| p.0).collect(),
+            values: points.iter().map(|p| p.1).collect(),
+        }
+    }
+
+    pub(crate) fn interpolate(&self, x: f64) -> f64 {
+        let (i, t) = locate(&self.x, x);
+        let i1 = (i + 1).min(self.x.len() - 1);
+        self.values[i] + (self.values[i1] - self.values[i]) * t
+    }
+}
+
+// Bilinear interpolation of a value over a rectangular grid
+#[derive(Clone)]
+pub(crate) struct Table2D {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    values: Vec<f64>, // Row major, indexed by [x][y]
+}
+
+impl Table2D {
+    // Builds the grid from (x, y, value) points, which must be finite and cover every (x, y)
+    // combination
+    pub(crate) fn from_points(points: &[(f64, f64, f64)]) -> Result<Table2D> {
+        if points
+            .iter()
+            .any(|&(x, y, value)| !x.is_finite() || !y.is_finite() || !value.is_finite())
+        {
+            return Err("table has a point which is not a finite number".into());
+        }
+
+        let axis = |select: fn(&(f64, f64, f64)) -> f64| {
+            let mut axis: Vec<f64> = points.iter().map(select).collect();
+            axis.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            axis.dedup();
+            axis
+        };
+        let x = axis(|p| p.0);
+        let y = axis(|p| p.1);
+
+        let mut values = vec![f64::NAN; x.len() * y.len()];
+        for (px, py, value) in points {
+            let i = x.iter().position(|v| v == px).unwrap();
+            let j = y.iter().position(|v| v == py).unwrap();
+            values[i * y.len() + j] = *value;
+        }
+        if values.is_empty() || values.iter().any(|v| v.is_nan()) {
+            return Err("table is not a complete grid".into());
+        }
+
+        Ok(Table2D { x, y, values })
+    }
+
+    pub(crate) fn interpolate(&self, x: f64, y: f64) -> f64 {
+        let (i, tx) = locate(&self.x, x);
+        let (j, ty) = locate(&self.y, y);
+        let at = |i: usize, j: usize| self.values[i * self.y.len() + j];
+        let i1 = (i + 1).min(self.x.len() - 1);
+        let j1 = (j + 1).min(self.y.len() - 1);
+
+        let low = at(i, j) + (at(i, j1) - at(i, j)) * ty;
+        let high = at(i1, j) + (at(i1, j1) - at(i1, j)) * ty;
+        low + (high - low) * tx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    const DT: f64 = 0.001;
+
+    // Runs a filter on a unit step from 0.0, returning the output after each of the times
+    fn step_response(mut update: impl FnMut(f64, f64) -> f64, times: &[f64]) -> Vec<f64> {
+        update(0.0, DT);
+        let mut outputs = Vec::new();
+        let mut time = 0.0;
+        for &until in times {
+            let mut output = 0.0;
+            while time < until - DT / 2.0 {
+                output = update(1.0, DT);
+                time += DT;
+            }
+            outputs.push(output);
+        }
+        outputs
+    }
+
+    // The steady state amplitude of the response to a unit sine wave
+    fn sine_gain(mut update: impl FnMut(f64, f64) -> f64, frequency: f64) -> f64 {
+        let w = 2.0 * PI * frequency;
+        let cycles = 20.0;
+        let steps = (cycles / frequency / DT) as usize;
+        let mut amplitude: f64 = 0.0;
+        for step in 0..steps {
+            let output = update((w * step as f64 * DT).sin(), DT);
+            // Skip the transient
+            if step > steps / 2 {
+                amplitude = amplitude.max(output.abs());
+            }
+        }
+        amplitude
+    }
+
+    #[test]
+    fn low_pass_step() {
+        let mut filter = LowPass::new(0.5);
+        let outputs = step_response(|input, dt| filter.update(input, dt), &[0.5, 1.5, 5.0]);
+
+        // 63% of the step after one time constant, 95% after three
+        assert_close(outputs[0], 1.0 - (-1.0f64).exp(), 1e-3);
+        assert_close(outputs[1], 1.0 - (-3.0f64).exp(), 1e-3);
+        assert_close(outputs[2], 1.0, 1e-4);
+    }
+
+    #[test]
+    fn low_pass_starts_from_the_first_sample() {
+        let mut filter = LowPass::new(0.5);
+
+        assert_close(filter.update(3.0, DT), 3.0, 1e-12);
+        filter.reset();
+        assert_close(filter.update(-2.0, DT), -2.0, 1e-12);
+    }
+
+    #[test]
+    fn low_pass_cutoff() {
+        let mut filter = LowPass::from_cutoff(2.0);
+
+        assert_close(
+            sine_gain(|input, dt| filter.update(input, dt), 2.0),
+            0.5f64.sqrt(),
+            0.01,
+        );
+        assert!(sine_gain(|input, dt| filter.update(input, dt), 20.0) < 0.11);
+    }
+
+    #[test]
+    fn washout_step() {
+        let mut filter = Washout::new(0.5);
+        let outputs = step_response(|input, dt| filter.update(input, dt), &[DT, 0.5, 5.0]);
+
+        // Passes the step, then washes it out
+        assert_close(outputs[0], 1.0, 0.01);
+        assert_close(outputs[1], (-1.0f64).exp(), 1e-3);
+        assert_close(outputs[2], 0.0, 1e-4);
+    }
+
+    #[test]
+    fn lead_lag_step() {
+        let (lead, lag) = (0.4, 0.2);
+        let mut filter = LeadLag::new(lead, lag);
+        let outputs = step_response(|input, dt| filter.update(input, dt), &[DT, lag, 5.0]);
+
+        // Jumps by lead/lag, then settles to the step with the lag time constant
+        assert_close(outputs[0], lead / lag, 0.01);
+        assert_close(outputs[1], 1.0 + (lead / lag - 1.0) * (-1.0f64).exp(), 1e-3);
+        assert_close(outputs[2], 1.0, 1e-4);
+    }
+
+    #[test]
+    fn lead_lag_high_frequency_gain() {
+        let (lead, lag) = (0.4, 0.1);
+        let mut filter = LeadLag::new(lead, lag);
+
+        assert_close(
+            sine_gain(|input, dt| filter.update(input, dt), 0.01),
+            1.0,
+            0.01,
+        );
+        assert_close(
+            sine_gain(|input, dt| filter.update(input, dt), 50.0),
+            lead / lag,
+            0.1,
+        );
+    }
+
+    #[test]
+    fn notch_frequency_response() {
+        let (frequency, zeta_zero, zeta_pole) = (8.0, 0.05, 0.5);
+        let new = || Notch::new(frequency, zeta_zero, zeta_pole);
+        let gain = |f: f64| {
+            let r = f / frequency;
+            (1.0 - r * r).hypot(2.0 * zeta_zero * r) / (1.0 - r * r).hypot(2.0 * zeta_pole * r)
+        };
+
+        let mut filter = new();
+        assert_close(filter.update(2.0, DT), 2.0, 1e-12);
+        for _ in 0..1000 {
+            filter.update(2.0, DT);
+        }
+        assert_close(filter.update(2.0, DT), 2.0, 1e-9);
+
+        // The notch depth is the ratio of the dampings
+        for f in [8.0, 0.5, 4.0, 40.0] {
+            let mut filter = new();
+            assert_close(
+                sine_gain(|input, dt| filter.update(input, dt), f),
+                gain(f),
+                0.01,
+            );
+        }
+        assert_close(gain(frequency), zeta_zero / zeta_pole, 1e-12);
+    }
+
+    #[test]
+    fn rate_limiter() {
+        let mut limiter = RateLimiter::asymmetric(2.0, 1.0);
+
+        assert_close(limiter.update(5.0, 0.1), 5.0, 1e-12);
+        assert_close(limiter.update(10.0, 0.5), 6.0, 1e-12);
+        assert_close(limiter.update(0.0, 0.5), 5.5, 1e-12);
+        assert_close(limiter.update(5.6, 0.5), 5.6, 1e-12);
+        limiter.reset(-1.0);
+        assert_close(limiter.update(0.0, 0.1), -0.8, 1e-12);
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut hysteresis = Hysteresis::new(1.0, 2.0);
+
+        assert!(!hysteresis.update(1.5));
+        assert!(hysteresis.update(2.5));
+        assert!(hysteresis.update(1.5));
+        assert!(!hysteresis.update(0.5));
+        assert!(!hysteresis.update(1.5));
+    }
+
+    #[test]
+    fn confirm_timer() {
+        let mut timer = ConfirmTimer::new(1.0);
+
+        assert!(!timer.update(true, 0.6));
+        assert!(!timer.update(false, 0.6));
+        assert!(!timer.update(true, 0.6));
+        assert!(timer.update(true, 0.6));
+        timer.reset();
+        assert!(!timer.confirmed());
+    }
+
+    #[test]
+    fn latch() {
+        let mut latch = Latch::default();
+
+        assert!(latch.update(true, false));
+        assert!(latch.update(false, false));
+        assert!(!latch.update(true, true));
+        assert!(!latch.update(false, false));
+    }
+
+    #[test]
+    fn table_1d() {
+        let table = Table1D::new(&[(0.0, 1.0), (10.0, 3.0), (20.0, 2.0)]);
+
+        assert_close(table.interpolate(-5.0), 1.0, 1e-12);
+        assert_close(table.interpolate(5.0), 2.0, 1e-12);
+        assert_close(table.interpolate(10.0), 3.0, 1e-12);
+        assert_close(table.interpolate(15.0), 2.5, 1e-12);
+        assert_close(table.interpolate(25.0), 2.0, 1e-12);
+    }
+
+    #[test]
+    fn table_2d() {
+        let table = Table2D::from_points(&[
+            (0.0, 0.0, 0.0),
+            (0.0, 1.0, 1.0),
+            (2.0, 0.0, 2.0),
+            (2.0, 1.0, 5.0),
+        ])
+        .unwrap();
+
+        assert_close(table.interpolate(1.0, 0.5), 2.0, 1e-12);
+        assert_close(table.interpolate(2.0, 0.5), 3.5, 1e-12);
+        assert_close(table.interpolate(3.0, 2.0), 5.0, 1e-12);
+        assert!(Table2D::from_points(&[(0.0, 0.0, 0.0), (1.0, 1.0, 1.0)]).is_err());
+    }
+
+    #[test]
+    fn table_2d_rejects_non_finite_points() {
+        let grid = |x: f64, value: f64| {
+            Table2D::from_points(&[
+                (0.0, 0.0, 0.0),
+                (0.0, 1.0, 1.0),
+                (x, 0.0, 2.0),
+                (x, 1.0, value),
+            ])
+        };
+
+        assert!(grid(f64::NAN, 5.0).is_err());
+        assert!(grid(f64::INFINITY, 5.0).is_err());
+        assert!(grid(2.0, f64::NAN).is_err());
+        assert!(grid(2.0, 5.0).is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+// The individual flight control surfaces.
+//
+// Each surface has its own actuator, hydraulic supply and failure, so that a failure can affect
+// one side only. Their positions are published for the displays (e.g. the F/CTL ECAM page)
+// through A32NX_FBW_<SURFACE>_POSITION, and they can be failed through
+// A32NX_FBW_FAILURE_<SURFACE>, e.g. A32NX_FBW_FAILURE_LEFT_AILERON.
+
+use crate::{actuator::Actuator, allocation::SPOILER_PANELS, units::Pressure};
+use msfs::msfs::legacy::NamedVariable;
+
+// The L:vars a surface is published and failed through
+struct SurfaceVariables {
+    position: NamedVariable,
+    failure: NamedVariable,
+}
+
+pub(crate) struct Surface {
+    position_var: &'static str,
+    failure_var: &'static str,
+    variables: Option<SurfaceVariables>, // Registered in init
+    actuator: Actuator,
+    position: f64,
+}
+
+impl Surface {
+    pub(crate) fn new(
+        position_var: &'static str,
+        failure_var: &'static str,
+        actuator: Actuator,
+    ) -> Surface {
+        Surface {
+            position_var,
+            failure_var,
+            variables: None,
+            actuator,
+            position: 0.0,
+        }
+    }
+
+    pub(crate) fn init(&mut self) {
+        self.variables = Some(SurfaceVariables {
+            position: NamedVariable::from(self.position_var),
+            failure: NamedVariable::from(self.failure_var),
+        });
+    }
+
+    // Moves the surface towards the command, unless it has failed or lost its hydraulic supply,
+    // and publishes its position
+    pub(crate) fn update(
+        &mut self,
+        command: f64,
+        powered: bool,
+        dynamic_pressure: Pressure,
+        dt: f64,
+    ) -> f64 {
+        let failed = match &self.variables {
+            Some(variables) => variables.failure.get_value::<f64>() != 0.0,
+            None => false,
+        };
+        self.position = self
+            .actuator
+            .update(command, powered && !failed, dynamic_pressure, dt);
+        if let Some(variables) = &self.variables {
+            variables.position.set_value(self.position);
+        }
+
+        self.position
+    }
+
+    // Moves the surface to a position, without publishing it
+    pub(crate) fn reset(&mut self, position: f64) {
+        self.position = self.actuator.reset(position);
+    }
+
+    pub(crate) fn position(&self) -> f64 {
+        self.position
+    }
+}
+
+// The ailerons, positive trailing edge down (from -1.0 full up to +1.0 full down).
+// 40 degrees/second over 17 degrees, they blow back close to VMO.
+pub(crate) fn aileron(position_var: &'static str, failure_var: &'static str) -> Surface {
+    Surface::new(
+        position_var,
+        failure_var,
+        Actuator::new(-1.0, 1.0, 2.3, 0.05)
+            .with_blowback(Pressure::from_pounds_per_square_foot(380.0)),
+    )
+}
+
+// The elevators, positive trailing edge up (from -1.0 full down to +1.0 full up).
+// 30 degrees/second over 16 degrees up and 11.5 degrees down.
+pub(crate) fn elevator(position_var: &'static str, failure_var: &'static str) -> Surface {
+    Surface::new(
+        position_var,
+        failure_var,
+        Actuator::new(-1.0, 1.0, 1.9, 0.05),
+    )
+}
+
+// The trimmable horizontal stabiliser, in degrees (from -4.0 full nose down to +13.5 full nose
+// up). Its screw jack is irreversible, so it stays in place when it is no longer driven.
+pub(crate) fn ths() -> Surface {
+    Surface::new(
+        "A32NX_FBW_THS_POSITION",
+        "A32NX_FBW_FAILURE_THS",
+        Actuator::new(-4.0, 13.5, 1.0, 0.0).irreversible(),
+    )
+}
+
+// The spoiler panels of a wing, from 0.0 retracted to 1.0 fully extended.
+// 40 degrees/second over 50 degrees.
+pub(crate) fn spoilers(
+    position_vars: [&'static str; SPOILER_PANELS],
+    failure_vars: [&'static str; SPOILER_PANELS],
+) -> [Surface; SPOILER_PANELS] {
+    let spoiler = |panel: usize| {
+        Surface::new(
+            position_vars[panel],
+            failure_vars[panel],
+            Actuator::new(0.0, 1.0, 0.8, 0.05),
+        )
+    };
+    [spoiler(0), spoiler(1), spoiler(2), spoiler(3), spoiler(4)]
+}
@@ -0,0 +1,422 @@
+// Typed physical quantities, so that values in different units can't be mixed up.
+//
+// Each quantity is stored in the unit the FBW mostly works in, and converted explicitly with the
+// from_<unit> constructors and <unit> accessors. Quantities of the same kind can be added,
+// subtracted, compared, and scaled by a plain number.
+//
+// Sign conventions, which are the opposite of the sim attitude SimVars:
+// - Pitch: + is nose up, - is nose down
+// - Roll: + is right wing down, - is left wing down
+// Use Angle::from_sim_attitude and AngularVelocity::from_sim_body_rate to convert.
+//
+// Not every conversion is used by the laws yet
+#![allow(dead_code)]
+
+use crate::clamp;
+use std::{
+    f64::consts::PI,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+macro_rules! quantity {
+    ($name:ident, $from_unit:ident, $unit:ident) => {
+        #[derive(Default, Clone, Copy, PartialEq, PartialOrd, Debug, serde::Deserialize)]
+        #[serde(transparent)]
+        pub(crate) struct $name(f64);
+
+        impl $name {
+            pub(crate) const fn $from_unit(value: f64) -> $name {
+                $name(value)
+            }
+
+            pub(crate) fn $unit(self) -> f64 {
+                self.0
+            }
+
+            pub(crate) fn abs(self) -> $name {
+                $name(self.0.abs())
+            }
+
+            pub(crate) fn signum(self) -> f64 {
+                self.0.signum()
+            }
+
+            pub(crate) fn min(self, other: $name) -> $name {
+                $name(self.0.min(other.0))
+            }
+
+            pub(crate) fn max(self, other: $name) -> $name {
+                $name(self.0.max(other.0))
+            }
+
+            pub(crate) fn clamp(self, min: $name, max: $name) -> $name {
+                $name(clamp(self.0, min.0, max.0))
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+            fn add(self, other: $name) -> $name {
+                $name(self.0 + other.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: $name) {
+                self.0 += other.0;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+            fn sub(self, other: $name) -> $name {
+                $name(self.0 - other.0)
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: $name) {
+                self.0 -= other.0;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $name {
+            type Output = $name;
+            fn mul(self, factor: f64) -> $name {
+                $name(self.0 * factor)
+            }
+        }
+
+        impl Mul<$name> for f64 {
+            type Output = $name;
+            fn mul(self, quantity: $name) -> $name {
+                $name(self * quantity.0)
+            }
+        }
+
+        impl Div<f64> for $name {
+            type Output = $name;
+            fn div(self, divisor: f64) -> $name {
+                $name(self.0 / divisor)
+            }
+        }
+
+        // The ratio of two quantities of the same kind is a plain number
+        impl Div for $name {
+            type Output = f64;
+            fn div(self, divisor: $name) -> f64 {
+                self.0 / divisor.0
+            }
+        }
+    };
+}
+
+quantity!(Angle, from_degrees, degrees);
+quantity!(AngularVelocity, from_degrees_per_second, degrees_per_second);
+quantity!(Velocity, from_knots, knots);
+quantity!(Length, from_feet, feet);
+quantity!(Density, from_slugs_per_cubic_foot, slugs_per_cubic_foot);
+quantity!(Mass, from_pounds, pounds);
+quantity!(Temperature, from_kelvin, kelvin);
+quantity!(Force, from_pounds_force, pounds_force);
+quantity!(
+    Pressure,
+    from_pounds_per_square_foot,
+    pounds_per_square_foot
+);
+
+const FEET_PER_METER: f64 = 3.28084;
+const FEET_PER_SECOND_PER_KNOT: f64 = 1.68781;
+const KILOGRAMS_PER_POUND: f64 = 0.453592;
+const KILOGRAMS_PER_CUBIC_METER_PER_SLUG_PER_CUBIC_FOOT: f64 = 515.379;
+const NEWTONS_PER_POUND_FORCE: f64 = 4.44822;
+const PASCALS_PER_POUND_PER_SQUARE_FOOT: f64 = 47.8803;
+
+impl Angle {
+    // The sim reports pitch and bank positive nose down and left wing down
+    pub(crate) fn from_sim_attitude(degrees: f64) -> Angle {
+        Angle(-degrees)
+    }
+
+    pub(crate) fn from_radians(radians: f64) -> Angle {
+        Angle(radians.to_degrees())
+    }
+
+    pub(crate) fn radians(self) -> f64 {
+        self.0.to_radians()
+    }
+
+    pub(crate) fn sin(self) -> f64 {
+        self.radians().sin()
+    }
+
+    pub(crate) fn cos(self) -> f64 {
+        self.radians().cos()
+    }
+
+    // The angle swept in dt seconds at the given rate
+    pub(crate) fn swept(rate: AngularVelocity, dt: f64) -> Angle {
+        Angle(rate.0 * dt)
+    }
+}
+
+impl AngularVelocity {
+    // The sim body rotation rates follow the same signs as its attitude
+    pub(crate) fn from_sim_body_rate(degrees_per_second: f64) -> AngularVelocity {
+        AngularVelocity(-degrees_per_second)
+    }
+
+    // The average rate to sweep an angle in dt seconds
+    pub(crate) fn from_change(angle: Angle, dt: f64) -> AngularVelocity {
+        AngularVelocity(angle.0 / dt)
+    }
+}
+
+impl Velocity {
+    pub(crate) fn from_feet_per_second(feet_per_second: f64) -> Velocity {
+        Velocity(feet_per_second / FEET_PER_SECOND_PER_KNOT)
+    }
+
+    pub(crate) fn feet_per_second(self) -> f64 {
+        self.0 * FEET_PER_SECOND_PER_KNOT
+    }
+
+    pub(crate) fn from_meters_per_second(meters_per_second: f64) -> Velocity {
+        Velocity::from_feet_per_second(meters_per_second * FEET_PER_METER)
+    }
+
+    pub(crate) fn meters_per_second(self) -> f64 {
+        self.feet_per_second() / FEET_PER_METER
+    }
+}
+
+// A velocity in the local earth axes: north, east and up
+#[derive(Default, Clone, Copy, PartialEq, Debug, serde::Deserialize)]
+pub(crate) struct VelocityVector {
+    pub(crate) north: Velocity,
+    pub(crate) east: Velocity,
+    pub(crate) up: Velocity,
+}
+
+impl VelocityVector {
+    pub(crate) fn magnitude(self) -> Velocity {
+        Velocity(
+            (self.north.0 * self.north.0 + self.east.0 * self.east.0 + self.up.0 * self.up.0)
+                .sqrt(),
+        )
+    }
+
+    // The magnitude of the velocity in the horizontal plane
+    pub(crate) fn horizontal(self) -> Velocity {
+        Velocity((self.north.0 * self.north.0 + self.east.0 * self.east.0).sqrt())
+    }
+
+    // The angle of the velocity above the horizon
+    pub(crate) fn elevation(self) -> Angle {
+        Angle::from_radians(self.up.0.atan2(self.horizontal().0))
+    }
+
+    // The direction of the horizontal velocity, clockwise from north in [0, 360) degrees
+    pub(crate) fn azimuth(self) -> Angle {
+        Angle::from_radians(self.east.0.atan2(self.north.0).rem_euclid(2.0 * PI))
+    }
+}
+
+impl Sub for VelocityVector {
+    type Output = VelocityVector;
+    fn sub(self, other: VelocityVector) -> VelocityVector {
+        VelocityVector {
+            north: self.north - other.north,
+            east: self.east - other.east,
+            up: self.up - other.up,
+        }
+    }
+}
+
+impl Length {
+    pub(crate) fn from_meters(meters: f64) -> Length {
+        Length(meters * FEET_PER_METER)
+    }
+
+    pub(crate) fn meters(self) -> f64 {
+        self.0 / FEET_PER_METER
+    }
+}
+
+impl Density {
+    pub(crate) fn kilograms_per_cubic_meter(self) -> f64 {
+        self.0 * KILOGRAMS_PER_CUBIC_METER_PER_SLUG_PER_CUBIC_FOOT
+    }
+
+    // The dynamic pressure of air of this density flowing at the given true airspeed
+    pub(crate) fn dynamic_pressure(self, tas: Velocity) -> Pressure {
+        Pressure(0.5 * self.0 * tas.feet_per_second() * tas.feet_per_second())
+    }
+}
+
+impl Mass {
+    pub(crate) fn from_kilograms(kilograms: f64) -> Mass {
+        Mass(kilograms / KILOGRAMS_PER_POUND)
+    }
+
+    pub(crate) fn kilograms(self) -> f64 {
+        self.0 * KILOGRAMS_PER_POUND
+    }
+}
+
+impl Temperature {
+    pub(crate) fn from_celsius(celsius: f64) -> Temperature {
+        Temperature(celsius + 273.15)
+    }
+
+    pub(crate) fn celsius(self) -> f64 {
+        self.0 - 273.15
+    }
+}
+
+impl Force {
+    pub(crate) fn newtons(self) -> f64 {
+        self.0 * NEWTONS_PER_POUND_FORCE
+    }
+}
+
+impl Pressure {
+    pub(crate) fn from_pascals(pascals: f64) -> Pressure {
+        Pressure(pascals / PASCALS_PER_POUND_PER_SQUARE_FOOT)
+    }
+
+    pub(crate) fn pascals(self) -> f64 {
+        self.0 * PASCALS_PER_POUND_PER_SQUARE_FOOT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    #[test]
+    fn conversions() {
+        assert_close(Velocity::from_knots(1.0).feet_per_second(), 1.68781, 1e-12);
+        assert_close(
+            Velocity::from_knots(100.0).meters_per_second(),
+            51.444,
+            1e-3,
+        );
+        assert_close(
+            Velocity::from_meters_per_second(51.444).knots(),
+            100.0,
+            1e-3,
+        );
+        assert_close(Length::from_meters(1000.0).feet(), 3280.84, 1e-9);
+        assert_close(Length::from_feet(35000.0).meters(), 10668.0, 0.01);
+        assert_close(Mass::from_kilograms(1000.0).pounds(), 2204.62, 0.01);
+        assert_close(Mass::from_pounds(2204.62).kilograms(), 1000.0, 0.01);
+        assert_close(Temperature::from_celsius(15.0).kelvin(), 288.15, 1e-12);
+        assert_close(Temperature::from_kelvin(216.65).celsius(), -56.5, 1e-12);
+        assert_close(Force::from_pounds_force(1000.0).newtons(), 4448.22, 1e-9);
+        assert_close(
+            Pressure::from_pascals(101_325.0).pounds_per_square_foot(),
+            2116.2,
+            0.1,
+        );
+        assert_close(
+            Density::from_slugs_per_cubic_foot(0.002377).kilograms_per_cubic_meter(),
+            1.225,
+            1e-3,
+        );
+        assert_close(Angle::from_radians(PI).degrees(), 180.0, 1e-12);
+    }
+
+    #[test]
+    fn sim_sign_conventions() {
+        // The sim is positive nose down and left wing down
+        assert_close(Angle::from_sim_attitude(5.0).degrees(), -5.0, 1e-12);
+        assert_close(
+            AngularVelocity::from_sim_body_rate(-3.0).degrees_per_second(),
+            3.0,
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn rates_and_angles() {
+        let rate = AngularVelocity::from_degrees_per_second(3.0);
+
+        assert_close(Angle::swept(rate, 0.5).degrees(), 1.5, 1e-12);
+        assert_close(
+            AngularVelocity::from_change(Angle::from_degrees(1.5), 0.5).degrees_per_second(),
+            3.0,
+            1e-12,
+        );
+        assert_close(Angle::from_degrees(30.0).sin(), 0.5, 1e-12);
+        assert_close(Angle::from_degrees(60.0).cos(), 0.5, 1e-12);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Velocity::from_knots(100.0);
+        let b = Velocity::from_knots(40.0);
+
+        assert_close((a - b).knots(), 60.0, 1e-12);
+        assert_close((a + b * 2.0).knots(), 180.0, 1e-12);
+        assert_close((0.5 * -a).knots(), -50.0, 1e-12);
+        assert_close(b / a, 0.4, 1e-12);
+        assert_eq!(a.min(b), b);
+        assert_eq!((-a).clamp(-b, b), Velocity::from_knots(-40.0));
+    }
+
+    #[test]
+    fn velocity_vector() {
+        let velocity = |north, east, up| VelocityVector {
+            north: Velocity::from_knots(north),
+            east: Velocity::from_knots(east),
+            up: Velocity::from_knots(up),
+        };
+
+        assert_close(velocity(30.0, 40.0, 0.0).magnitude().knots(), 50.0, 1e-12);
+        assert_close(
+            velocity(30.0, 40.0, 120.0).magnitude().knots(),
+            130.0,
+            1e-12,
+        );
+        assert_close(
+            velocity(30.0, 40.0, 120.0).horizontal().knots(),
+            50.0,
+            1e-12,
+        );
+        assert_close(
+            velocity(0.0, 100.0, 100.0).elevation().degrees(),
+            45.0,
+            1e-12,
+        );
+        assert_close(
+            velocity(0.0, 100.0, -100.0).elevation().degrees(),
+            -45.0,
+            1e-12,
+        );
+        assert_close(velocity(100.0, 0.0, 0.0).azimuth().degrees(), 0.0, 1e-12);
+        assert_close(velocity(0.0, 100.0, 0.0).azimuth().degrees(), 90.0, 1e-12);
+        assert_close(velocity(-100.0, 0.0, 0.0).azimuth().degrees(), 180.0, 1e-12);
+        assert_close(velocity(0.0, -100.0, 0.0).azimuth().degrees(), 270.0, 1e-12);
+
+        let difference = velocity(100.0, 20.0, 5.0) - velocity(10.0, 20.0, -5.0);
+        assert_eq!(difference, velocity(90.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn dynamic_pressure() {
+        // 0.5 * 0.002377 * (200 * 1.68781)^2
+        let q = Density::from_slugs_per_cubic_foot(0.002377)
+            .dynamic_pressure(Velocity::from_knots(200.0));
+
+        assert_close(q.pounds_per_square_foot(), 135.427, 1e-3);
+    }
+}
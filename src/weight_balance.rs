@@ -0,0 +1,248 @@
+use crate::{
+    clamp,
+    fbw::FBW,
+    flight_model,
+    limits::Configuration,
+    units::{Mass, Velocity},
+    Result,
+};
+
+// The CG envelope, from the [WEIGHT_AND_BALANCE] section of flight_model.cfg.
+// The limits are fractions of the reference chord.
+#[derive(Clone, Copy)]
+struct CgEnvelope {
+    forward_limit: f64,
+    aft_limit: f64,
+}
+
+impl CgEnvelope {
+    fn load() -> Result<CgEnvelope> {
+        Ok(CgEnvelope {
+            forward_limit: flight_model::value("WEIGHT_AND_BALANCE", "CG_forward_limit")?,
+            aft_limit: flight_model::value("WEIGHT_AND_BALANCE", "CG_aft_limit")?,
+        })
+    }
+
+    fn position(&self, cg: f64) -> CgPosition {
+        if cg < self.forward_limit {
+            CgPosition::ForwardOfLimit
+        } else if cg > self.aft_limit {
+            CgPosition::AftOfLimit
+        } else {
+            CgPosition::InEnvelope
+        }
+    }
+}
+
+// Where the CG is relative to its envelope
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum CgPosition {
+    InEnvelope,
+    ForwardOfLimit,
+    AftOfLimit,
+}
+
+// Weight and balance dependent behaviour:
+// - The characteristic speeds, published as A32NX_FBW_VS1G, A32NX_FBW_VLS and
+//   A32NX_FBW_GREEN_DOT (knots)
+// - A pitch gain factor, as the aircraft is more stable with a forward CG and less with an aft CG
+// - The CG envelope monitoring, published as A32NX_FBW_CG_WARNING (0 = in the envelope,
+//   1 = forward of the forward limit, 2 = aft of the aft limit)
+#[derive(Clone)]
+pub(crate) struct WeightAndBalance {
+    envelope: Option<CgEnvelope>, // Loaded in init
+    pub(crate) cg: f64,           // The longitudinal CG as a fraction of the reference chord
+    pub(crate) cg_position: CgPosition,
+    pub(crate) vs1g: Velocity, // 1g stall speed in the current configuration
+    pub(crate) vls: Velocity,  // Lowest selectable speed
+    pub(crate) green_dot: Velocity, // Best lift to drag ratio speed, in the clean configuration
+    pub(crate) pitch_gain_factor: f64,
+}
+impl Default for WeightAndBalance {
+    fn default() -> Self {
+        WeightAndBalance {
+            envelope: None,
+            cg: Self::REFERENCE_CG,
+            cg_position: CgPosition::InEnvelope,
+            vs1g: Velocity::default(),
+            vls: Velocity::default(),
+            green_dot: Velocity::default(),
+            pitch_gain_factor: 1.0,
+        }
+    }
+}
+
+impl WeightAndBalance {
+    // The CG the pitch gains are tuned for
+    const REFERENCE_CG: f64 = 0.25;
+    // Pitch gain change per unit of chord the CG is forward of the reference
+    const PITCH_GAIN_PER_CG: f64 = 1.0;
+
+    // The A320 reference wing area in square meters
+    const WING_AREA: f64 = 122.6;
+    const SEA_LEVEL_DENSITY: f64 = 1.225;
+    const GRAVITY: f64 = 9.806_65;
+
+    pub(crate) fn init(&mut self) -> Result<()> {
+        self.envelope = Some(CgEnvelope::load()?);
+
+        Ok(())
+    }
+
+    pub(crate) fn update(&mut self, ctx: &FBW) -> Result<()> {
+        use msfs::msfs::legacy::NamedVariable;
+
+        let weight = ctx.data.weight();
+        let configuration = ctx.speed_limits.configuration;
+        self.cg = ctx.data.cg_longitudinal() / 100.0;
+
+        self.cg_position = self.envelope.map_or(CgPosition::InEnvelope, |envelope| {
+            envelope.position(self.cg)
+        });
+
+        self.vs1g = Self::vs1g(weight, configuration);
+        self.vls = self.vs1g * Self::vls_factor(configuration);
+        self.green_dot = Self::green_dot(weight);
+
+        self.pitch_gain_factor = Self::pitch_gain_factor(self.cg);
+
+        NamedVariable::from("A32NX_FBW_VS1G").set_value(self.vs1g.knots());
+        NamedVariable::from("A32NX_FBW_VLS").set_value(self.vls.knots());
+        NamedVariable::from("A32NX_FBW_GREEN_DOT").set_value(self.green_dot.knots());
+        NamedVariable::from("A32NX_FBW_CG_WARNING").set_value(match self.cg_position {
+            CgPosition::InEnvelope => 0.0,
+            CgPosition::ForwardOfLimit => 1.0,
+            CgPosition::AftOfLimit => 2.0,
+        });
+
+        Ok(())
+    }
+
+    // A forward CG needs more elevator for the same response, an aft CG less
+    fn pitch_gain_factor(cg: f64) -> f64 {
+        clamp(
+            1.0 + Self::PITCH_GAIN_PER_CG * (Self::REFERENCE_CG - cg),
+            0.5,
+            1.5,
+        )
+    }
+
+    // The 1g stall speed (as an equivalent airspeed) at which the weight is carried at the
+    // maximum lift coefficient of the configuration.
+    // TODO: The maximum lift coefficients are estimates, tuned to the published VLS at 64 t
+    fn vs1g(weight: Mass, configuration: Configuration) -> Velocity {
+        let max_lift_coefficient = match configuration {
+            Configuration::Clean => 1.40,
+            Configuration::Conf1 => 1.80,
+            Configuration::Conf1F => 2.10,
+            Configuration::Conf2 => 2.35,
+            Configuration::Conf3 => 2.50,
+            Configuration::Full => 2.85,
+        };
+        let lift = weight.kilograms() * Self::GRAVITY;
+        Velocity::from_meters_per_second(
+            (2.0 * lift / (Self::SEA_LEVEL_DENSITY * Self::WING_AREA * max_lift_coefficient))
+                .sqrt(),
+        )
+    }
+
+    // VLS is 1.28 VS1g in the clean configuration and CONF 1, and 1.23 VS1g in the landing
+    // configurations, from the FCOM in 1.22.30 under "Characteristic Speeds"
+    fn vls_factor(configuration: Configuration) -> f64 {
+        match configuration {
+            Configuration::Clean | Configuration::Conf1 => 1.28,
+            _ => 1.23,
+        }
+    }
+
+    // Green dot speed, approximated below FL200 as twice the weight in tonnes plus 85 knots
+    fn green_dot(weight: Mass) -> Velocity {
+        Velocity::from_knots(2.0 * weight.kilograms() / 1000.0 + 85.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_close;
+
+    const CONFIGURATIONS: [Configuration; 6] = [
+        Configuration::Clean,
+        Configuration::Conf1,
+        Configuration::Conf1F,
+        Configuration::Conf2,
+        Configuration::Conf3,
+        Configuration::Full,
+    ];
+
+    #[test]
+    fn vs1g() {
+        // sqrt(2 * 64000 * 9.80665 / (1.225 * 122.6 * 2.85)) = 54.15 m/s
+        let weight = Mass::from_kilograms(64_000.0);
+        let vs1g = WeightAndBalance::vs1g(weight, Configuration::Full);
+        assert_close(vs1g.meters_per_second(), 54.15, 0.01);
+        assert_close(
+            (vs1g * WeightAndBalance::vls_factor(Configuration::Full)).knots(),
+            129.5,
+            0.1,
+        );
+
+        // Proportional to the square root of the weight
+        let heavier = WeightAndBalance::vs1g(weight * 4.0, Configuration::Full);
+        assert_close(heavier / vs1g, 2.0, 1e-12);
+
+        // Decreasing as the high lift devices extend
+        for pair in CONFIGURATIONS.windows(2) {
+            assert!(
+                WeightAndBalance::vs1g(weight, pair[1]) < WeightAndBalance::vs1g(weight, pair[0])
+            );
+        }
+    }
+
+    #[test]
+    fn vls_factor() {
+        let factors: Vec<f64> = CONFIGURATIONS
+            .iter()
+            .map(|&configuration| WeightAndBalance::vls_factor(configuration))
+            .collect();
+
+        assert_eq!(factors, [1.28, 1.28, 1.23, 1.23, 1.23, 1.23]);
+    }
+
+    #[test]
+    fn green_dot() {
+        assert_close(
+            WeightAndBalance::green_dot(Mass::from_kilograms(60_000.0)).knots(),
+            205.0,
+            1e-9,
+        );
+        assert_close(
+            WeightAndBalance::green_dot(Mass::from_kilograms(75_000.0)).knots(),
+            235.0,
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn pitch_gain_factor() {
+        assert_close(WeightAndBalance::pitch_gain_factor(0.25), 1.0, 1e-12);
+        assert_close(WeightAndBalance::pitch_gain_factor(0.15), 1.1, 1e-12);
+        assert_close(WeightAndBalance::pitch_gain_factor(0.40), 0.85, 1e-12);
+        assert_close(WeightAndBalance::pitch_gain_factor(-1.0), 1.5, 1e-12);
+        assert_close(WeightAndBalance::pitch_gain_factor(2.0), 0.5, 1e-12);
+    }
+
+    #[test]
+    fn cg_position() {
+        let envelope = CgEnvelope {
+            forward_limit: 0.16,
+            aft_limit: 0.4,
+        };
+
+        assert_eq!(envelope.position(0.10), CgPosition::ForwardOfLimit);
+        assert_eq!(envelope.position(0.16), CgPosition::InEnvelope);
+        assert_eq!(envelope.position(0.30), CgPosition::InEnvelope);
+        assert_eq!(envelope.position(0.40), CgPosition::InEnvelope);
+        assert_eq!(envelope.position(0.45), CgPosition::AftOfLimit);
+    }
+}